@@ -0,0 +1,76 @@
+//! Long programs, over large `ι` lists and deep stacks, where the cost of
+//! each step used to grow with the size of the stack.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use kuhi::{
+    parser::{parse, Loc, Token},
+    vm::Env,
+};
+
+fn tokens(src: &str) -> Vec<(Token, Loc)> {
+    let mut loc = Loc {
+        start: 0,
+        end: 0,
+        line: 1,
+        column: 1,
+    };
+    parse(src, &mut loc).unwrap()
+}
+
+fn run(tokens: &[(Token, Loc)]) {
+    let mut env = Env::new(tokens.to_vec());
+    env.run().unwrap();
+    black_box(env.stack);
+}
+
+/// `+1 ×2 …` on a list, with more of them waiting below it on the stack
+fn lists(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lists");
+    group.sample_size(10);
+
+    for n in [1_000, 10_000] {
+        let steps = "+1 ×2 -3 ".repeat(20);
+        let program = tokens(&format!("{steps}ι{n} ι{n} ι{n} ι{n}"));
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &program, |b, program| {
+            b.iter(|| run(program))
+        });
+    }
+    group.finish();
+}
+
+/// Summing `n` values pushed one by one, so the stack is `n` deep
+fn deep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep");
+    group.sample_size(10);
+
+    for n in [100, 1_000] {
+        let program = tokens(&format!("{}{}", "+ ".repeat(n - 1), "7 ".repeat(n)));
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &program, |b, program| {
+            b.iter(|| run(program))
+        });
+    }
+    group.finish();
+}
+
+/// Calling user functions, some of them inverted
+fn calls(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calls");
+    group.sample_size(10);
+
+    for n in [100, 1_000] {
+        let program = tokens(&format!(
+            "f ← (+1 ×2)\ng ← (⁻¹f f)\n{}0",
+            "g f ".repeat(n)
+        ));
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &program, |b, program| {
+            b.iter(|| run(program))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lists, deep, calls);
+criterion_main!(benches);
@@ -0,0 +1,219 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{err::RuntimeError, value::Value};
+
+/// # Arrays
+///
+/// An n-dimensional array, its elements are stored flat in row-major order.
+///
+/// `shape` is the length of every axis, the first one being the outermost \
+/// `data` holds the `shape.iter().product()` elements
+///
+/// A scalar is an array of rank 0, with an empty shape and a single element.
+#[derive(Debug, Clone)]
+pub struct Array {
+    pub shape: Vec<usize>,
+    pub data: Vec<Value>,
+}
+
+impl Array {
+    pub fn new(shape: Vec<usize>, data: Vec<Value>) -> Self {
+        debug_assert_eq!(shape.iter().product::<usize>(), data.len());
+
+        Self { shape, data }
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    /// Lists are read as nested arrays, so they must be rectangular.
+    /// Any other value becomes a scalar.
+    pub fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Array(array) => Ok(array.clone()),
+            Value::List(vals) => {
+                let mut inner: Option<Vec<usize>> = None;
+                let mut data = vec![];
+
+                for val in vals {
+                    let array = Array::from_value(val)?;
+                    match &inner {
+                        Some(shape) if *shape != array.shape => {
+                            return Err(RuntimeError::ShapeMissmatch {
+                                first: shape.clone(),
+                                second: array.shape,
+                            })
+                        }
+                        Some(_) => {}
+                        None => inner = Some(array.shape.clone()),
+                    }
+                    data.extend(array.data);
+                }
+
+                let mut shape = vec![vals.len()];
+                shape.append(&mut inner.unwrap_or_default());
+                Ok(Array::new(shape, data))
+            }
+            scalar => Ok(Array::new(vec![], vec![scalar.clone()])),
+        }
+    }
+
+    /// Builds an array out of its major cells, which must all have the shape
+    /// `cell_shape`.
+    pub fn from_cells(cells: Vec<Value>, cell_shape: Vec<usize>) -> Result<Self, RuntimeError> {
+        let mut shape = vec![cells.len()];
+        shape.extend(&cell_shape);
+
+        let mut data = vec![];
+        for cell in cells {
+            let cell = Array::from_value(&cell)?;
+            if cell.shape != cell_shape {
+                return Err(RuntimeError::ShapeMissmatch {
+                    first: cell_shape,
+                    second: cell.shape,
+                });
+            }
+            data.extend(cell.data);
+        }
+
+        Ok(Array::new(shape, data))
+    }
+
+    /// The `i`th element along the first axis, which is a scalar for arrays
+    /// of rank 1.
+    pub fn cell(&self, i: usize) -> Value {
+        if self.rank() == 1 {
+            return self.data[i].clone();
+        }
+
+        let size = self.shape[1..].iter().product::<usize>();
+        Value::Array(Array::new(
+            self.shape[1..].to_vec(),
+            self.data[i * size..(i + 1) * size].to_vec(),
+        ))
+    }
+
+    pub fn cells(&self) -> Vec<Value> {
+        (0..self.shape.first().copied().unwrap_or(0))
+            .map(|i| self.cell(i))
+            .collect()
+    }
+
+    /// Nested lists, the inverse of `from_value`.
+    pub fn into_list(self) -> Value {
+        fn build(shape: &[usize], data: &mut impl Iterator<Item = Value>) -> Value {
+            match shape.split_first() {
+                None => data.next().unwrap(),
+                Some((len, rest)) => Value::List((0..*len).map(|_| build(rest, data)).collect()),
+            }
+        }
+
+        build(&self.shape, &mut self.data.into_iter())
+    }
+
+    /// The elements are reused from the start when there aren't enough of
+    /// them, as in APL.
+    pub fn reshape(self, shape: Vec<usize>) -> Result<Self, RuntimeError> {
+        // Any more can't be allocated
+        let max = isize::MAX as usize / std::mem::size_of::<Value>();
+        let len = shape
+            .iter()
+            .try_fold(1usize, |len, n| len.checked_mul(*n))
+            .filter(|len| *len <= max)
+            .ok_or(RuntimeError::ElementLimit(max))?;
+        if self.data.is_empty() && len != 0 {
+            return Err(RuntimeError::ShapeMissmatch {
+                first: self.shape,
+                second: shape,
+            });
+        }
+
+        let data = self.data.into_iter().cycle().take(len).collect();
+        Ok(Array::new(shape, data))
+    }
+
+    /// Reverses the order of the axes.
+    pub fn transpose(self) -> Self {
+        let shape = self.shape.iter().rev().copied().collect::<Vec<_>>();
+        let strides = Array::strides(&self.shape);
+
+        let mut data = Vec::with_capacity(self.data.len());
+        let mut index = vec![0; shape.len()];
+        for _ in 0..self.data.len() {
+            // `index` walks the new shape, which reads the old one backwards
+            let from = index
+                .iter()
+                .rev()
+                .zip(strides.iter())
+                .map(|(i, stride)| i * stride)
+                .sum::<usize>();
+            data.push(self.data[from].clone());
+
+            for axis in (0..shape.len()).rev() {
+                index[axis] += 1;
+                if index[axis] < shape[axis] {
+                    break;
+                }
+                index[axis] = 0;
+            }
+        }
+
+        Array::new(shape, data)
+    }
+
+    /// Applies `op` element-wise, following leading axis agreement: the shape
+    /// of one side must be a prefix of the other's, and each element of the
+    /// smaller array is paired with a whole cell of the bigger one.
+    pub fn broadcast(lhs: Value, rhs: Value, op: fn(Value, Value) -> Value) -> Value {
+        let (lhs, rhs) = match (Array::from_value(&lhs), Array::from_value(&rhs)) {
+            (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+            (Err(err), _) | (_, Err(err)) => return Value::InvalidState(err),
+        };
+
+        let (short, long) = if lhs.rank() <= rhs.rank() {
+            (&lhs, &rhs)
+        } else {
+            (&rhs, &lhs)
+        };
+        if !long.shape.starts_with(&short.shape) {
+            return Value::InvalidState(RuntimeError::ShapeMissmatch {
+                first: lhs.shape,
+                second: rhs.shape,
+            });
+        }
+
+        let cell = long.data.len() / short.data.len().max(1);
+        let mut data = Vec::with_capacity(long.data.len());
+        for (i, val) in long.data.iter().enumerate() {
+            let other = short.data[i / cell].clone();
+            let res = if lhs.rank() <= rhs.rank() {
+                op(other, val.clone())
+            } else {
+                op(val.clone(), other)
+            };
+
+            if let Value::InvalidState(_) = res {
+                return res;
+            }
+            data.push(res);
+        }
+
+        Value::Array(Array::new(long.shape.clone(), data))
+    }
+
+    /// How many elements to skip to move by one along each axis.
+    fn strides(shape: &[usize]) -> Vec<usize> {
+        let mut strides = vec![1; shape.len()];
+        for axis in (0..shape.len().saturating_sub(1)).rev() {
+            strides[axis] = strides[axis + 1] * shape[axis + 1];
+        }
+        strides
+    }
+}
+
+impl Display for Array {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.clone().into_list())
+    }
+}
@@ -1,41 +1,107 @@
 use std::collections::HashMap;
 
 use lazy_static::lazy_static;
-use rug::{Float, Integer};
+use rug::{ops::RemRounding, Float, Integer};
 
 use crate::{
+    array::Array,
     err::RuntimeError,
+    limits,
     value::{Value, PI},
 };
 
 pub type Stack = Vec<Value>;
-pub type RuntimeResult = Result<Vec<Value>, RuntimeError>;
-pub type Func = fn(Stack) -> RuntimeResult;
+pub type RuntimeResult = Result<(), RuntimeError>;
+pub type Func = fn(&mut Stack) -> RuntimeResult;
 
 lazy_static! {
     pub static ref BUILTINS: HashMap<char, Builtin> = HashMap::from([
-        ('.', Builtin::new(dup, |_| Err(RuntimeError::NoInverse), 1)),
-        (',', Builtin::new(pop, |_| Err(RuntimeError::NoInverse), 1)),
-        ('↕', Builtin::new(flip, flip, 2)),
-        ('↺', Builtin::new(roll, roll, 3)),
-        ('+', Builtin::new(add, sub, 2)),
-        ('-', Builtin::new(sub, add, 2)),
-        ('×', Builtin::new(mul, div, 2)),
-        ('÷', Builtin::new(div, mul, 2)),
-        ('ⁿ', Builtin::new(pow, root, 2)),
-        ('√', Builtin::new(root, pow, 2)),
-        ('◯', Builtin::new(sin, asin, 1)),
-        ('ⓔ', Builtin::new(sinh, asinh, 1)),
-        // ('Ⓞ', Builtin::new(sins, sins, 1)),
-        ('ι', Builtin::new(iota, |_| Err(RuntimeError::NoInverse), 1)),
+        ('.', Builtin::new(dup, Inverse::None, Signature::new(1, 2))),
+        (',', Builtin::new(pop, Inverse::None, Signature::new(1, 0))),
+        ('↕', Builtin::new(flip, Inverse::Whole(flip), Signature::new(2, 2))),
+        ('↺', Builtin::new(roll, Inverse::Whole(roll), Signature::new(3, 3))),
+        ('+', Builtin::new(add, Inverse::Binary(Some(sub), Some(sub)), Signature::new(2, 1))),
+        (
+            '-',
+            Builtin::new(
+                sub,
+                Inverse::Binary(Some(add), Some(__flipped::<'-'>)),
+                Signature::new(2, 1),
+            ),
+        ),
+        ('×', Builtin::new(mul, Inverse::Binary(Some(div), Some(div)), Signature::new(2, 1))),
+        (
+            '÷',
+            Builtin::new(
+                div,
+                Inverse::Binary(Some(mul), Some(__flipped::<'÷'>)),
+                Signature::new(2, 1),
+            ),
+        ),
+        ('ⁿ', Builtin::new(pow, Inverse::Binary(Some(root), Some(log)), Signature::new(2, 1))),
+        (
+            '√',
+            Builtin::new(
+                root,
+                Inverse::Binary(Some(pow), Some(__flipped::<'ₙ'>)),
+                Signature::new(2, 1),
+            ),
+        ),
+        (
+            'ₙ',
+            Builtin::new(
+                log,
+                Inverse::Binary(Some(__flipped::<'ⁿ'>), Some(__flipped::<'√'>)),
+                Signature::new(2, 1),
+            ),
+        ),
+        ('◯', Builtin::new(sin, Inverse::Whole(asin), Signature::new(1, 1))),
+        ('ⓔ', Builtin::new(sinh, Inverse::Whole(asinh), Signature::new(1, 1))),
+        // ('Ⓞ', Builtin::new(sins, Inverse::Whole(sins), 1)),
+        ('ι', Builtin::new(iota, Inverse::None, Signature::new(1, 1))),
+        ('⊤', Builtin::new(digits, Inverse::Whole(undigits), Signature::new(1, 1))),
+        ('⊥', Builtin::new(undigits, Inverse::Whole(digits), Signature::new(1, 1))),
+        ('⊔', Builtin::new(unpack, Inverse::None, Signature::variable(1))),
+        ('⊡', Builtin::new(pick, Inverse::None, Signature::new(2, 1))),
+        ('⊏', Builtin::new(select, Inverse::None, Signature::new(2, 1))),
+        ('↑', Builtin::new(take, Inverse::None, Signature::new(2, 1))),
+        ('↓', Builtin::new(drop, Inverse::None, Signature::new(2, 1))),
+        ('⊘', Builtin::new(slice, Inverse::None, Signature::new(2, 1))),
+        ('⊢', Builtin::new(first, Inverse::None, Signature::new(1, 1))),
+        ('⊣', Builtin::new(last, Inverse::None, Signature::new(1, 1))),
+        ('∧', Builtin::new(sort_up, Inverse::None, Signature::new(1, 1))),
+        ('∨', Builtin::new(sort_down, Inverse::None, Signature::new(1, 1))),
+        ('⍋', Builtin::new(grade_up, Inverse::None, Signature::new(1, 1))),
+        ('⍒', Builtin::new(grade_down, Inverse::None, Signature::new(1, 1))),
+        ('⍸', Builtin::new(search, Inverse::None, Signature::new(2, 1))),
+        ('⊐', Builtin::new(index_of, Inverse::None, Signature::new(2, 1))),
+        ('∊', Builtin::new(member_of, Inverse::None, Signature::new(2, 1))),
+        ('⍷', Builtin::new(unique, Inverse::None, Signature::new(1, 1))),
+        ('⊒', Builtin::new(count, Inverse::None, Signature::new(1, 1))),
+        ('∾', Builtin::new(join, Inverse::None, Signature::new(2, 1))),
+        ('⇌', Builtin::new(reverse, Inverse::Whole(reverse), Signature::new(1, 1))),
+        ('↻', Builtin::new(rotate, Inverse::Binary(Some(unrotate), None), Signature::new(2, 1))),
+        ('▽', Builtin::new(replicate, Inverse::None, Signature::new(2, 1))),
+        ('⊕', Builtin::new(group, Inverse::None, Signature::new(2, 1))),
+        ('⊜', Builtin::new(partition, Inverse::None, Signature::new(2, 1))),
+        ('◫', Builtin::new(windows, Inverse::None, Signature::new(2, 1))),
+        ('⊟', Builtin::new(chunk, Inverse::None, Signature::new(2, 1))),
+        ('⋈', Builtin::new(zip, Inverse::None, Signature::new(2, 1))),
+        ('⋉', Builtin::new(unzip, Inverse::None, Signature::new(1, 2))),
+        ('⊞', Builtin::new(table, Inverse::None, Signature::new(2, 1))),
+        ('△', Builtin::new(shape, Inverse::None, Signature::new(1, 1))),
+        ('↯', Builtin::new(reshape, Inverse::None, Signature::new(2, 1))),
+        ('⍤', Builtin::new(rank, Inverse::None, Signature::new(1, 1))),
+        ('⍉', Builtin::new(transpose, Inverse::Whole(transpose), Signature::new(1, 1))),
+        ('♭', Builtin::new(ravel, Inverse::None, Signature::new(1, 1))),
     ]);
 }
 
 /// # Builtin functions
 ///
 /// `action` is what the function doeso n the stack
-/// `inverse` is the reverse of what action would do
-/// `arity` is how many stack elements are affected by a function
+/// `inverse` is the reverse of what action would do, see `Inverse`
+/// `signature` is how many stack elements it takes and leaves, see `Signature`
 ///
 /// ## Examples
 ///
@@ -44,116 +110,315 @@ lazy_static! {
 /// ```
 pub struct Builtin {
     pub action: Func,
-    pub inverse: Func,
-    pub arity: usize,
+    pub inverse: Inverse,
+    pub signature: Signature,
+}
+
+/// # Stack signatures
+///
+/// How many values a function pops (`inputs`), and how many it pushes back
+/// (`outputs`), `None` when that depends on the values themselves, like `⊔`
+///
+/// ## Examples
+///
+/// ```text
+/// +   2 → 1
+/// .   1 → 2
+/// ⊔   1 → ?
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub inputs: usize,
+    pub outputs: Option<usize>,
+}
+
+impl Signature {
+    pub const fn new(inputs: usize, outputs: usize) -> Self {
+        Self {
+            inputs,
+            outputs: Some(outputs),
+        }
+    }
+
+    pub const fn variable(inputs: usize) -> Self {
+        Self {
+            inputs,
+            outputs: None,
+        }
+    }
+}
+
+/// # Inverses
+///
+/// How a builtin is undone, which for binary builtins depends on which
+/// operand is known.
+///
+/// `Whole` takes what the builtin left on the stack and gives back what it
+/// took, like `⇌` or `↕` \
+/// `Binary(left, right)` is for binary builtins `f y x`, `y` being the left
+/// operand and the top of the stack. Both are called with the fixed operand
+/// on top of the result: `left` keeps `y` fixed and gives back `x`, while
+/// `right` keeps `x` fixed and gives back `y`
+///
+/// ## Examples
+///
+/// ```text
+/// ⁿ2 3 => 9
+/// ⁻¹ⁿ2 9 => 3      # left, root
+/// ₙ3 9 => 2        # right, log
+/// ```
+pub enum Inverse {
+    None,
+    Whole(Func),
+    Binary(Option<Func>, Option<Func>),
 }
 
 impl Builtin {
-    pub fn new(action: Func, inverse: Func, arity: usize) -> Self {
+    pub fn new(action: Func, inverse: Inverse, signature: Signature) -> Self {
         Self {
             action,
             inverse,
-            arity,
+            signature,
+        }
+    }
+
+    pub fn call(&self, stack: &mut Stack) -> RuntimeResult {
+        if self.signature.inputs > stack.len() {
+            return Err(RuntimeError::InvalidPop {
+                len: stack.len(),
+                arity: self.signature.inputs,
+            });
+        }
+        (self.action)(stack)
+    }
+
+    pub fn call_inverse(&self, stack: &mut Stack) -> RuntimeResult {
+        if self.signature.inputs > stack.len() {
+            return Err(RuntimeError::InvalidPop {
+                len: stack.len(),
+                arity: self.signature.inputs,
+            });
+        }
+        match self.left_inverse() {
+            Some(inverse) => inverse(stack),
+            None => Err(RuntimeError::NoInverse),
+        }
+    }
+
+    /// Undoes a binary builtin knowing its right operand, see `Inverse`.
+    pub fn call_right_inverse(&self, stack: &mut Stack) -> RuntimeResult {
+        if self.signature.inputs > stack.len() {
+            return Err(RuntimeError::InvalidPop {
+                len: stack.len(),
+                arity: self.signature.inputs,
+            });
+        }
+        match self.right_inverse() {
+            Some(inverse) => inverse(stack),
+            None => Err(RuntimeError::NoInverse),
+        }
+    }
+
+    pub fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    /// What `⁻¹` uses, a binary builtin having its left operand fixed.
+    pub fn left_inverse(&self) -> Option<Func> {
+        match self.inverse {
+            Inverse::Whole(inverse) => Some(inverse),
+            Inverse::Binary(left, _) => left,
+            Inverse::None => None,
+        }
+    }
+
+    pub fn right_inverse(&self) -> Option<Func> {
+        match self.inverse {
+            Inverse::Binary(_, right) => right,
+            _ => None,
+        }
+    }
+}
+
+/// A closure, so builtins registered by the host can capture their state
+pub type CustomFunc = Box<dyn Fn(&mut Stack) -> RuntimeResult>;
+
+/// # Custom builtins
+///
+/// A builtin registered by the host with `Env::register`, shadowing the
+/// builtin with the same glyph if there is one. Like `Builtin`, it is called
+/// with the whole stack and gives it back, `inverse` being what `⁻¹` calls.
+///
+/// `name` is an ASCII spelling the REPL formats into the glyph, like `sqrt`
+/// is formatted into `√`
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::{builtins::{CustomBuiltin, Signature}, value::Value, vm::Env};
+///
+/// let mut env = Env::new(vec![]);
+/// env.register(
+///     'ð',
+///     CustomBuiltin::new(|stack| {
+///         let x = stack.pop().unwrap();
+///         stack.push(x.clone() + x);
+///         Ok(())
+///     }, Signature::new(1, 1))
+///     .named("double"),
+/// );
+/// ```
+pub struct CustomBuiltin {
+    pub action: CustomFunc,
+    pub inverse: Option<CustomFunc>,
+    pub signature: Signature,
+    pub name: Option<String>,
+}
+
+impl CustomBuiltin {
+    pub fn new(
+        action: impl Fn(&mut Stack) -> RuntimeResult + 'static,
+        signature: Signature,
+    ) -> Self {
+        Self {
+            action: Box::new(action),
+            inverse: None,
+            signature,
+            name: None,
         }
     }
 
-    pub fn call(&self, stack: Stack) -> RuntimeResult {
-        if self.arity > stack.len() {
+    pub fn with_inverse(mut self, inverse: impl Fn(&mut Stack) -> RuntimeResult + 'static) -> Self {
+        self.inverse = Some(Box::new(inverse));
+        self
+    }
+
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    pub fn call(&self, stack: &mut Stack) -> RuntimeResult {
+        if self.signature.inputs > stack.len() {
             return Err(RuntimeError::InvalidPop {
                 len: stack.len(),
-                arity: self.arity,
+                arity: self.signature.inputs,
             });
         }
         (self.action)(stack)
     }
 
-    pub fn call_inverse(&self, stack: Stack) -> RuntimeResult {
-        if self.arity > stack.len() {
+    /// Takes what `call` gives back, so it needs `signature.outputs` values,
+    /// when they are known
+    pub fn call_inverse(&self, stack: &mut Stack) -> RuntimeResult {
+        let Some(inverse) = &self.inverse else {
+            return Err(RuntimeError::NoInverse);
+        };
+        let arity = self.signature.outputs.unwrap_or(0);
+        if arity > stack.len() {
             return Err(RuntimeError::InvalidPop {
                 len: stack.len(),
-                arity: self.arity,
+                arity,
             });
         }
-        (self.inverse)(stack)
+        inverse(stack)
     }
 }
 
-fn dup(stack: Stack) -> RuntimeResult {
-    let ([x], mut stack) = __pop_n(stack);
+fn dup(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
 
     stack.push(x.clone());
     stack.push(x);
-    Ok(stack)
+    Ok(())
 }
 
-fn pop(stack: Stack) -> RuntimeResult {
-    let ([_], stack) = __pop_n(stack);
+fn pop(stack: &mut Stack) -> RuntimeResult {
+    let [_] = __pop_n(stack);
 
-    Ok(stack)
+    Ok(())
 }
 
-fn flip(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+fn flip(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
 
     stack.push(y);
     stack.push(x);
-    Ok(stack)
+    Ok(())
 }
 
-fn roll(stack: Stack) -> RuntimeResult {
-    let ([z, y, x], mut stack) = __pop_n(stack);
+fn roll(stack: &mut Stack) -> RuntimeResult {
+    let [z, y, x] = __pop_n(stack);
 
     stack.push(x);
     stack.push(y);
     stack.push(z);
-    Ok(stack)
+    Ok(())
 }
 
-fn add(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+fn add(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
 
     stack.push(x + y);
-    Ok(stack)
+    Ok(())
 }
 
-fn sub(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+fn sub(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
 
     stack.push(x + -y);
-    Ok(stack)
+    Ok(())
 }
 
-fn mul(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+fn mul(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
 
     stack.push(x * y);
-    Ok(stack)
+    Ok(())
 }
 
-fn div(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+fn div(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
 
     stack.push(x * y.reciprocal());
-    Ok(stack)
+    Ok(())
 }
 
 /// ⁿ2 3 => 9
-fn pow(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+fn pow(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
 
     stack.push(x.pow(&y));
-    Ok(stack)
+    Ok(())
+}
+
+/// √2 9 => 3
+fn root(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(x.root(&y));
+    Ok(())
 }
 
-fn root(stack: Stack) -> RuntimeResult {
-    let ([y, x], mut stack) = __pop_n(stack);
+/// ₙ2 8 => 3
+///
+/// The logarithm of the right operand, in the base of the left one.
+fn log(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(x.log(&y));
+    Ok(())
+}
 
-    stack.push(x.pow(&-y));
-    Ok(stack)
+/// Runs the builtin `C` with its operands swapped, for the inverses that
+/// need them the other way around.
+fn __flipped<const C: char>(stack: &mut Stack) -> RuntimeResult {
+    flip(stack)?;
+    BUILTINS[&C].call(stack)
 }
 
-fn sin(stack: Stack) -> RuntimeResult {
-    let ([x], mut stack) = __pop_n(stack);
+fn sin(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
 
     match x {
         Value::Integer(x) => {
@@ -184,7 +449,9 @@ fn sin(stack: Stack) -> RuntimeResult {
             let mut list = vec![];
             for val in vals.into_iter() {
                 // TODO: Better error reporting
-                list.push(sin(vec![val])?.first().unwrap().clone());
+                let mut inner = vec![val];
+                sin(&mut inner)?;
+                list.push(inner.pop().unwrap());
             }
             stack.push(Value::List(list));
         }
@@ -196,11 +463,11 @@ fn sin(stack: Stack) -> RuntimeResult {
         }
     }
 
-    Ok(stack)
+    Ok(())
 }
 
-fn asin(stack: Stack) -> RuntimeResult {
-    let ([x], mut stack) = __pop_n(stack);
+fn asin(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
 
     match x {
         Value::Integer(x) => {
@@ -234,11 +501,11 @@ fn asin(stack: Stack) -> RuntimeResult {
         }
     }
 
-    Ok(stack)
+    Ok(())
 }
 
-fn sinh(stack: Stack) -> RuntimeResult {
-    let ([x], mut stack) = __pop_n(stack);
+fn sinh(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
 
     match x {
         Value::Integer(x) => {
@@ -261,11 +528,11 @@ fn sinh(stack: Stack) -> RuntimeResult {
         }
     }
 
-    Ok(stack)
+    Ok(())
 }
 
-fn asinh(stack: Stack) -> RuntimeResult {
-    let ([x], mut stack) = __pop_n(stack);
+fn asinh(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
 
     match x {
         Value::Integer(x) => {
@@ -288,11 +555,11 @@ fn asinh(stack: Stack) -> RuntimeResult {
         }
     }
 
-    Ok(stack)
+    Ok(())
 }
 
-fn iota(stack: Stack) -> RuntimeResult {
-    let ([x], mut stack) = __pop_n(stack);
+fn iota(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
     let up_to = if let Value::Integer(x) = x {
         if !x.is_positive() {
             return Err(RuntimeError::InvalidIotaValue);
@@ -308,19 +575,842 @@ fn iota(stack: Stack) -> RuntimeResult {
             got: x.types().join(", "),
         });
     };
+    limits::elements(usize::try_from(up_to).unwrap_or(usize::MAX))?;
 
     stack.push(Value::List(
         (1..=up_to)
             .map(|n| Value::Integer(Integer::from(n)))
             .collect(),
     ));
-    Ok(stack)
+    Ok(())
+}
+
+/// ⊤123 => [1 2 3]
+///
+/// The base 10 digits of a natural number, the most significant first.
+fn digits(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let n = match &x {
+        Value::Integer(n) if !n.is_negative() => n,
+        _ => {
+            return Err(RuntimeError::TypeMissmatch {
+                expected: "Natural".to_string(),
+                got: x.types().join(", "),
+            })
+        }
+    };
+    stack.push(Value::List(
+        n.to_string()
+            .chars()
+            .map(|d| Value::Integer(Integer::from(d.to_digit(10).unwrap())))
+            .collect(),
+    ));
+    Ok(())
+}
+
+/// ⊥1‿2‿3 => 123
+///
+/// Digits don't need to be below 10, they carry over like in `⊥1‿12 => 22`.
+fn undigits(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let mut n = Value::Integer(Integer::ZERO);
+    for d in __cells(x)? {
+        n = n * Value::Integer(Integer::from(10));
+        if let Value::InvalidState(err) = n {
+            return Err(err);
+        }
+        n = n + d;
+        if let Value::InvalidState(err) = n {
+            return Err(err);
+        }
+    }
+
+    stack.push(n);
+    Ok(())
+}
+
+/// ⊔⟨1 2 3⟩ => 1 2 3
+///
+/// The first element ends up on top of the stack.
+fn unpack(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    match x {
+        Value::Tuple(vals) => stack.extend(vals.into_iter().rev()),
+        _ => {
+            return Err(RuntimeError::TypeMissmatch {
+                expected: "Tuple".to_string(),
+                got: x.types().join(", "),
+            })
+        }
+    }
+
+    Ok(())
+}
+
+/// ⊡1 ⟨1 π 2‿3⟩ => π \
+/// ⊡1‿0 ↯2‿2 ι4 => 3
+///
+/// A list of indices picks along one axis after the other.
+fn pick(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    match y {
+        Value::List(indices) => {
+            stack.push(indices.iter().try_fold(x, __pick)?);
+        }
+        index => stack.push(__pick(x, &index)?),
+    }
+
+    Ok(())
+}
+
+/// ⊏3‿1‿1 ι5 => [4 2 2]
+fn select(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let Value::List(indices) = y else {
+        return Err(RuntimeError::TypeMissmatch {
+            expected: "List".to_string(),
+            got: y.types().join(", "),
+        });
+    };
+    stack.push(__on_cells(x, |cells| {
+        indices
+            .iter()
+            .map(|index| Ok(cells[__index(index, cells.len())?].clone()))
+            .collect()
+    })?);
+    Ok(())
+}
+
+/// ↑2 ι5 => [1 2] \
+/// ↑⁻2 ι5 => [4 5]
+fn take(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |mut cells| {
+        let (n, from_end) = __count(&y)?;
+        if n > cells.len() {
+            return Err(RuntimeError::IndexOutOfRange {
+                index: __integer(&y)?,
+                len: cells.len(),
+            });
+        }
+
+        Ok(if from_end {
+            cells.split_off(cells.len() - n)
+        } else {
+            cells.truncate(n);
+            cells
+        })
+    })?);
+    Ok(())
+}
+
+/// ↓2 ι5 => [3 4 5] \
+/// ↓⁻2 ι5 => [1 2 3]
+///
+/// Dropping more elements than there are leaves an empty list.
+fn drop(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |mut cells| {
+        let (n, from_end) = __count(&y)?;
+        let n = n.min(cells.len());
+
+        Ok(if from_end {
+            cells.truncate(cells.len() - n);
+            cells
+        } else {
+            cells.split_off(n)
+        })
+    })?);
+    Ok(())
+}
+
+/// ⊘1‿3 ι5 => [2 3] \
+/// ⊘1‿⁻1 ι5 => [2 3 4] \
+/// ⊘⁻2‿5 ι5 => [4 5]
+///
+/// From the first index up to, but excluding, the second one.
+fn slice(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let bounds = match &y {
+        Value::List(bounds) if bounds.len() == 2 => bounds,
+        _ => {
+            return Err(RuntimeError::TypeMissmatch {
+                expected: "List of 2 Integers".to_string(),
+                got: y.types().join(", "),
+            })
+        }
+    };
+    stack.push(__on_cells(x, |mut cells| {
+        // The end of the list is a valid bound, ⁻1 still being the last element
+        let len = cells.len();
+        let bound = |bound: &Value| {
+            let n = __integer(bound)?;
+            let last = if n.is_negative() { len } else { len + 1 };
+            __index(bound, last).map_err(|_| RuntimeError::IndexOutOfRange { index: n, len })
+        };
+        let (start, end) = (bound(&bounds[0])?, bound(&bounds[1])?);
+
+        if start > end {
+            return Err(RuntimeError::InvalidSlice { start, end });
+        }
+        cells.truncate(end);
+        Ok(cells.split_off(start))
+    })?);
+    Ok(())
+}
+
+/// ⊢ι5 => 1
+fn first(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(__pick(x, &Value::Integer(Integer::from(0)))?);
+    Ok(())
+}
+
+/// ⊣ι5 => 5
+fn last(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(__pick(x, &Value::Integer(Integer::from(-1)))?);
+    Ok(())
+}
+
+/// △↯2‿3 ι6 => [2 3]
+fn shape(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let array = Array::from_value(&x)?;
+    stack.push(Value::List(
+        array
+            .shape
+            .into_iter()
+            .map(|n| Value::Integer(Integer::from(n)))
+            .collect(),
+    ));
+    Ok(())
+}
+
+/// ↯2‿2 ι3 => [[1 2] [3 1]]
+fn reshape(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let shape = match &y {
+        Value::List(vals) => vals.iter().map(__natural).collect::<Result<Vec<_>, _>>()?,
+        n => vec![__natural(n)?],
+    };
+    limits::elements(
+        shape
+            .iter()
+            .try_fold(1usize, |len, n| len.checked_mul(*n))
+            .unwrap_or(usize::MAX),
+    )?;
+    stack.push(Value::Array(Array::from_value(&x)?.reshape(shape)?));
+    Ok(())
+}
+
+/// ⍤↯2‿3 ι6 => 2
+fn rank(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(Value::Integer(Integer::from(Array::from_value(&x)?.rank())));
+    Ok(())
+}
+
+/// ⍉↯2‿3 ι6 => [[1 4] [2 5] [3 6]]
+fn transpose(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(Value::Array(Array::from_value(&x)?.transpose()));
+    Ok(())
+}
+
+/// ♭↯2‿3 ι6 => [1 2 3 4 5 6]
+fn ravel(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(Value::List(Array::from_value(&x)?.data));
+    Ok(())
+}
+
+/// One element of a tuple, a list, or a cell of an array.
+fn __pick(x: Value, index: &Value) -> Result<Value, RuntimeError> {
+    match x {
+        Value::Tuple(mut vals) | Value::List(mut vals) => {
+            let i = __index(index, vals.len())?;
+            Ok(vals.swap_remove(i))
+        }
+        Value::Array(array) if array.rank() > 0 => Ok(array.cell(__index(index, array.shape[0])?)),
+        _ => Err(RuntimeError::TypeMissmatch {
+            expected: "List".to_string(),
+            got: x.types().join(", "),
+        }),
+    }
+}
+
+/// Runs `f` on the elements of a list, or on the cells along the first axis
+/// of an array, and puts the result back together the same way.
+fn __on_cells(
+    x: Value,
+    f: impl FnOnce(Vec<Value>) -> Result<Vec<Value>, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    match x {
+        Value::List(vals) => Ok(Value::List(f(vals)?)),
+        Value::Array(array) if array.rank() > 0 => {
+            let cell_shape = array.shape[1..].to_vec();
+            Ok(Value::Array(Array::from_cells(
+                f(array.cells())?,
+                cell_shape,
+            )?))
+        }
+        _ => Err(RuntimeError::TypeMissmatch {
+            expected: "List".to_string(),
+            got: x.types().join(", "),
+        }),
+    }
+}
+
+/// A count of elements, and whether they are counted from the end.
+fn __count(n: &Value) -> Result<(usize, bool), RuntimeError> {
+    let n = __integer(n)?;
+    // Too big to be a valid count either way
+    let count = n.clone().abs().to_usize().unwrap_or(usize::MAX);
+    Ok((count, n.is_negative()))
+}
+
+fn __integer(n: &Value) -> Result<Integer, RuntimeError> {
+    match n {
+        Value::Integer(n) => Ok(n.clone()),
+        _ => Err(RuntimeError::TypeMissmatch {
+            expected: "Integer".to_string(),
+            got: n.types().join(", "),
+        }),
+    }
+}
+
+/// Reads a length or a count.
+fn __natural(n: &Value) -> Result<usize, RuntimeError> {
+    match n {
+        Value::Integer(n) if !n.is_negative() => n.to_usize().ok_or(RuntimeError::TypeMissmatch {
+            expected: "Natural".to_string(),
+            got: "Integer".to_string(),
+        }),
+        _ => Err(RuntimeError::TypeMissmatch {
+            expected: "Natural".to_string(),
+            got: n.types().join(", "),
+        }),
+    }
+}
+
+/// ∧3‿1‿2 => [1 2 3]
+///
+/// The sort is stable, see `Value`'s `Ord` for how values are ordered.
+fn sort_up(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |mut cells| {
+        cells.sort();
+        Ok(cells)
+    })?);
+    Ok(())
+}
+
+/// ∨3‿1‿2 => [3 2 1]
+fn sort_down(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |mut cells| {
+        cells.sort_by(|a, b| b.cmp(a));
+        Ok(cells)
+    })?);
+    Ok(())
+}
+
+/// ⍋3‿1‿2 => [1 2 0]
+///
+/// The indices that would sort the list, so `⊏⍋x x` is `∧x`.
+fn grade_up(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let cells = __cells(x)?;
+    stack.push(__indices(__grade(&cells)));
+    Ok(())
+}
+
+/// ⍒3‿1‿2 => [0 2 1]
+fn grade_down(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let cells = __cells(x)?;
+    let mut grade = (0..cells.len()).collect::<Vec<_>>();
+    grade.sort_by(|&i, &j| cells[j].cmp(&cells[i]));
+    stack.push(__indices(grade));
+    Ok(())
+}
+
+/// ⍸4 1‿3‿5‿7 => 2
+///
+/// Binary search in a list sorted upwards, giving where the value would have
+/// to be inserted to keep it sorted, before any equal values.
+fn search(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let cells = __cells(x)?;
+    stack.push(Value::Integer(Integer::from(
+        cells.partition_point(|cell| *cell < y),
+    )));
+    Ok(())
+}
+
+/// ⊐3 5‿4‿3 => 2
+///
+/// The length of the list when the value isn't in it.
+fn index_of(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let cells = __cells(x)?;
+    let i = cells
+        .iter()
+        .position(|cell| *cell == y)
+        .unwrap_or(cells.len());
+    stack.push(Value::Integer(Integer::from(i)));
+    Ok(())
+}
+
+/// ∊3 5‿4‿3 => 1
+fn member_of(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let cells = __cells(x)?;
+    stack.push(Value::Integer(Integer::from(cells.contains(&y) as u8)));
+    Ok(())
+}
+
+/// ⍷3‿1‿3‿2‿1 => [3 1 2]
+///
+/// Keeps the first of each value, in the order they appear.
+fn unique(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |cells| {
+        let firsts = __runs(&cells).into_iter().map(|run| run[0]);
+        Ok(firsts.map(|i| cells[i].clone()).collect())
+    })?);
+    Ok(())
+}
+
+/// ⊒3‿1‿3‿2‿1 => [2 2 1]
+///
+/// How many times each value occurs, in the same order as `⍷`.
+fn count(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let cells = __cells(x)?;
+    stack.push(Value::List(
+        __runs(&cells)
+            .into_iter()
+            .map(|run| Value::Integer(Integer::from(run.len())))
+            .collect(),
+    ));
+    Ok(())
+}
+
+/// Indices of the elements in sorted order, equal ones keeping their order.
+fn __grade(cells: &[Value]) -> Vec<usize> {
+    let mut grade = (0..cells.len()).collect::<Vec<_>>();
+    grade.sort_by(|&i, &j| cells[i].cmp(&cells[j]));
+    grade
+}
+
+/// Groups the indices of equal elements, ordered by first appearance.
+fn __runs(cells: &[Value]) -> Vec<Vec<usize>> {
+    let grade = __grade(cells);
+
+    let mut runs: Vec<Vec<usize>> = vec![];
+    for i in grade {
+        match runs.last_mut() {
+            Some(run) if cells[run[0]] == cells[i] => run.push(i),
+            _ => runs.push(vec![i]),
+        }
+    }
+    runs.sort_by_key(|run| run[0]);
+    runs
+}
+
+fn __indices(indices: Vec<usize>) -> Value {
+    Value::List(
+        indices
+            .into_iter()
+            .map(|i| Value::Integer(Integer::from(i)))
+            .collect(),
+    )
+}
+
+/// ∾1‿2 3‿4 => [1 2 3 4] \
+/// ∾0 ι3 => [0 1 2 3]
+///
+/// Values that aren't lists are joined as a single element.
+fn join(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let mut front = match y {
+        Value::List(_) | Value::Array(_) => __cells(y)?,
+        y => vec![y],
+    };
+    let joined = match x {
+        Value::List(_) | Value::Array(_) => __on_cells(x, |cells| {
+            front.extend(cells);
+            Ok(front)
+        })?,
+        x => {
+            front.push(x);
+            Value::List(front)
+        }
+    };
+    stack.push(__checked(joined)?);
+    Ok(())
+}
+
+/// ⇌ι3 => [3 2 1]
+fn reverse(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |mut cells| {
+        cells.reverse();
+        Ok(cells)
+    })?);
+    Ok(())
+}
+
+/// ↻1 ι5 => [2 3 4 5 1] \
+/// ↻⁻1 ι5 => [5 1 2 3 4]
+fn rotate(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(__rotate(x, __integer(&y)?)?);
+    Ok(())
+}
+
+fn unrotate(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(__rotate(x, -__integer(&y)?)?);
+    Ok(())
+}
+
+/// ▽1‿0‿2 ι3 => [1 3 3] \
+/// ▽2 ι2 => [1 1 2 2]
+fn replicate(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    stack.push(__on_cells(x, |cells| {
+        let counts: Vec<usize> = match &y {
+            Value::List(counts) if counts.len() != cells.len() => {
+                return Err(RuntimeError::ShapeMissmatch {
+                    first: vec![counts.len()],
+                    second: vec![cells.len()],
+                })
+            }
+            Value::List(counts) => counts.iter().map(__natural).collect::<Result<_, _>>()?,
+            n => vec![__natural(n)?; cells.len()],
+        };
+        limits::elements(counts.iter().fold(0usize, |len, n| len.saturating_add(*n)))?;
+
+        Ok(cells
+            .into_iter()
+            .zip(counts)
+            .flat_map(|(cell, n)| std::iter::repeat_n(cell, n))
+            .collect())
+    })?);
+    Ok(())
+}
+
+/// ⊕0‿1‿0 ι3 => [⟨1 3⟩ ⟨2⟩] \
+/// ⊕0‿1‿1‿0 ι4 => [[1 4] [2 3]]
+///
+/// Groups the elements sharing the same key, ordered by first appearance of
+/// the key. Groups of different lengths are tuples, see `__parts`.
+fn group(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let keys = __cells(y)?;
+    let cells = __cells(x)?;
+    if keys.len() != cells.len() {
+        return Err(RuntimeError::ShapeMissmatch {
+            first: vec![keys.len()],
+            second: vec![cells.len()],
+        });
+    }
+
+    stack.push(__parts(
+        __runs(&keys)
+            .into_iter()
+            .map(|run| run.into_iter().map(|i| cells[i].clone()).collect())
+            .collect(),
+    ));
+    Ok(())
+}
+
+/// ⊜1‿1‿0‿1 ι4 => [⟨1 2⟩ ⟨4⟩] \
+/// ⊜1‿1‿2‿2 ι4 => [[1 2] [3 4]]
+///
+/// Splits the list into runs of consecutive equal keys, the elements with a
+/// key of 0 are left out. Runs of different lengths are tuples, see
+/// `__parts`.
+fn partition(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let keys = __cells(y)?
+        .iter()
+        .map(__integer)
+        .collect::<Result<Vec<_>, _>>()?;
+    let cells = __cells(x)?;
+    if keys.len() != cells.len() {
+        return Err(RuntimeError::ShapeMissmatch {
+            first: vec![keys.len()],
+            second: vec![cells.len()],
+        });
+    }
+
+    let mut parts: Vec<Vec<Value>> = vec![];
+    let mut last = Integer::ZERO;
+    for (key, cell) in keys.into_iter().zip(cells) {
+        match parts.last_mut() {
+            _ if key.is_zero() => {}
+            Some(part) if key == last => part.push(cell),
+            _ => parts.push(vec![cell]),
+        }
+        last = key;
+    }
+
+    stack.push(__parts(parts));
+    Ok(())
+}
+
+/// ◫2 ι4 => [[1 2] [2 3] [3 4]]
+fn windows(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let n = __natural(&y)?;
+    let cells = __cells(x)?;
+    // There are no windows when they are longer than the list
+    let count = (cells.len() + 1).saturating_sub(n);
+    limits::elements(count.saturating_mul(n))?;
+    stack.push(Value::List(
+        (0..count)
+            .map(|i| Value::List(cells[i..i + n].to_vec()))
+            .collect(),
+    ));
+    Ok(())
+}
+
+/// ⊟2 ι4 => [[1 2] [3 4]] \
+/// ⊟2 ι5 => [⟨1 2⟩ ⟨3 4⟩ ⟨5⟩]
+///
+/// The last chunk is shorter when the length isn't a multiple of the size,
+/// the chunks then being tuples, see `__parts`.
+fn chunk(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let n = match __natural(&y)? {
+        0 => {
+            return Err(RuntimeError::TypeMissmatch {
+                expected: "Positive Integer".to_string(),
+                got: "0".to_string(),
+            })
+        }
+        n => n,
+    };
+    let cells = __cells(x)?;
+    stack.push(__parts(cells.chunks(n).map(<[Value]>::to_vec).collect()));
+    Ok(())
+}
+
+/// ⋈1‿2 3‿4 => [[1 3] [2 4]]
+fn zip(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let (fst, snd) = (__cells(y)?, __cells(x)?);
+    if fst.len() != snd.len() {
+        return Err(RuntimeError::ShapeMissmatch {
+            first: vec![fst.len()],
+            second: vec![snd.len()],
+        });
+    }
+
+    stack.push(__checked(Value::List(
+        fst.into_iter()
+            .zip(snd)
+            .map(|(a, b)| Value::List(vec![a, b]))
+            .collect(),
+    ))?);
+    Ok(())
+}
+
+/// ⋉[[1 3] [2 4]] => 1‿2 3‿4
+///
+/// The first list ends up on top of the stack, so `⋈⋉x` is `x`.
+fn unzip(stack: &mut Stack) -> RuntimeResult {
+    let [x] = __pop_n(stack);
+
+    let (mut fst, mut snd) = (vec![], vec![]);
+    for pair in __cells(x)? {
+        match __cells(pair)?.as_slice() {
+            [a, b] => {
+                fst.push(a.clone());
+                snd.push(b.clone());
+            }
+            pair => {
+                return Err(RuntimeError::ShapeMissmatch {
+                    first: vec![2],
+                    second: vec![pair.len()],
+                })
+            }
+        }
+    }
+
+    stack.push(Value::List(snd));
+    stack.push(Value::List(fst));
+    Ok(())
+}
+
+/// ⊞1‿2 3‿4 => [[[1 3] [1 4]] [[2 3] [2 4]]]
+///
+/// Every element of the first list paired with every element of the second.
+fn table(stack: &mut Stack) -> RuntimeResult {
+    let [y, x] = __pop_n(stack);
+
+    let (rows, cols) = (__cells(y)?, __cells(x)?);
+    // Each pair is a list of two
+    limits::elements(rows.len().saturating_mul(cols.len()).saturating_mul(3))?;
+    stack.push(__checked(Value::List(
+        rows.into_iter()
+            .map(|a| {
+                Value::List(
+                    cols.iter()
+                        .map(|b| Value::List(vec![a.clone(), b.clone()]))
+                        .collect(),
+                )
+            })
+            .collect(),
+    ))?);
+    Ok(())
+}
+
+fn __rotate(x: Value, n: Integer) -> Result<Value, RuntimeError> {
+    __on_cells(x, |mut cells| {
+        if !cells.is_empty() {
+            // `cells.len()` fits, so the remainder does too
+            let n = n.rem_euc(Integer::from(cells.len())).to_usize().unwrap();
+            cells.rotate_left(n);
+        }
+        Ok(cells)
+    })
+}
+
+/// A list of `parts`, which are tuples when they aren't all the same length,
+/// as lists can't be ragged.
+fn __parts(parts: Vec<Vec<Value>>) -> Value {
+    let ragged = parts.windows(2).any(|pair| pair[0].len() != pair[1].len());
+    let part = if ragged { Value::Tuple } else { Value::List };
+
+    Value::List(parts.into_iter().map(part).collect())
+}
+
+/// `x`, failing when it has lists with elements of different shapes, like
+/// `[1 [2]]`, which later steps can't work on.
+fn __checked(x: Value) -> Result<Value, RuntimeError> {
+    __shape(&x)?;
+    Ok(x)
+}
+
+/// The lengths of the nested lists of `x`. Tuples may differ from each other
+/// like in `Value::validate_list`, but not from the other elements.
+fn __shape(x: &Value) -> Result<Vec<usize>, RuntimeError> {
+    let vals = match x {
+        Value::List(vals) => vals,
+        Value::Array(array) => return Ok(array.shape.clone()),
+        _ => return Ok(vec![]),
+    };
+
+    let mut first: Option<(&Value, Vec<usize>)> = None;
+    for val in vals {
+        let shape = __shape(val)?;
+        match &first {
+            None => first = Some((val, shape)),
+            Some((other, cell))
+                if matches!(other, Value::Tuple(_)) != matches!(val, Value::Tuple(_)) =>
+            {
+                return Err(RuntimeError::ListTypeMissmatch {
+                    first: __kind(other, cell),
+                    second: __kind(val, &shape),
+                })
+            }
+            Some((_, cell)) if *cell == shape || matches!(val, Value::Tuple(_)) => {}
+            Some((other, cell)) => {
+                return Err(match (cell.first(), shape.first()) {
+                    (Some(a), Some(b)) if a != b => RuntimeError::ListElementSizeMissmatch {
+                        first: *a,
+                        second: *b,
+                    },
+                    _ => RuntimeError::ListTypeMissmatch {
+                        first: __kind(other, cell),
+                        second: __kind(val, &shape),
+                    },
+                })
+            }
+        }
+    }
+
+    let mut shape = vec![vals.len()];
+    shape.extend(first.map(|(_, cell)| cell).unwrap_or_default());
+    Ok(shape)
+}
+
+fn __kind(x: &Value, shape: &[usize]) -> String {
+    match shape {
+        [] => x.types().join(", "),
+        shape => format!("List {shape:?}"),
+    }
+}
+
+/// The elements of a list, or the cells along the first axis of an array.
+fn __cells(x: Value) -> Result<Vec<Value>, RuntimeError> {
+    match x {
+        Value::List(vals) => Ok(vals),
+        Value::Array(array) if array.rank() > 0 => Ok(array.cells()),
+        _ => Err(RuntimeError::TypeMissmatch {
+            expected: "List".to_string(),
+            got: x.types().join(", "),
+        }),
+    }
+}
+
+/// Resolves `index` into a position of something of length `len`, negative
+/// indices count from the end.
+fn __index(index: &Value, len: usize) -> Result<usize, RuntimeError> {
+    let index = __integer(index)?;
+
+    let i = if index.is_negative() {
+        index.clone() + len
+    } else {
+        index.clone()
+    };
+    match i.to_usize() {
+        Some(i) if i < len => Ok(i),
+        _ => Err(RuntimeError::IndexOutOfRange { index, len }),
+    }
 }
 
 /// As the program is ran from right to left, the resulting array will be in reverse.
-fn __pop_n<const N: usize>(stack: Vec<Value>) -> ([Value; N], Stack) {
-    let mut stack = stack.clone();
+fn __pop_n<const N: usize>(stack: &mut Stack) -> [Value; N] {
     // Already checked previously that the size is correct, so invariants hold
-    let values = [(); N].map(|_| unsafe { stack.pop().unwrap_unchecked() });
-    (values, stack)
+    [(); N].map(|_| unsafe { stack.pop().unwrap_unchecked() })
 }
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    builtins::{Builtin, CustomBuiltin, BUILTINS},
+    err::RuntimeError,
+    parser::{Loc, Token},
+    value::Value,
+    vm::Env,
+};
+
+/// # Programs
+///
+/// Tokens lowered to instructions, in the order they are ran. Literals are
+/// built once into `constants`, and `spans` gives the location of each
+/// instruction in the source, for errors.
+///
+/// ## Examples
+///
+/// ```text
+/// +1 ×2 3   =>   Push(0) Push(1) Builtin(×) Push(2) Builtin(+)
+///                constants: [3 2 1]
+/// ```
+#[derive(Default)]
+pub struct Program {
+    pub code: Vec<Instr>,
+    pub constants: Vec<Value>,
+    pub spans: Vec<Loc>,
+}
+
+pub enum Instr {
+    /// Pushes `constants[i]`
+    Push(usize),
+    Dup,
+    Pop,
+    Minus,
+
+    /// Looked up when compiling, they can't change while running
+    Builtin(&'static Builtin),
+    Inverse(&'static Builtin),
+    RightInverse(&'static Builtin),
+    Custom(Rc<CustomBuiltin>),
+    CustomInverse(Rc<CustomBuiltin>),
+
+    /// User functions are looked up when called, they may be defined later
+    Call(char),
+    CallInverse(char),
+
+    /// Runs on the values of the list on top of the stack
+    Scope(Rc<Program>),
+    /// Runs on an empty stack, pushing what it leaves as a tuple
+    Tuple(Rc<Program>),
+}
+
+/// Lowers `tokens`, failing on what can't be inverted.
+///
+/// Function bodies are inlined, and so are inverses, which are built when
+/// compiling, except for user functions.
+pub fn compile(
+    tokens: &[(Token, Loc)],
+    builtins: &HashMap<char, Rc<CustomBuiltin>>,
+) -> Result<Program, (RuntimeError, Loc)> {
+    let mut compiler = Compiler {
+        program: Program::default(),
+        builtins,
+    };
+    compiler.tokens(tokens)?;
+
+    Ok(compiler.program)
+}
+
+struct Compiler<'a> {
+    program: Program,
+    builtins: &'a HashMap<char, Rc<CustomBuiltin>>,
+}
+
+impl Compiler<'_> {
+    fn tokens(&mut self, tokens: &[(Token, Loc)]) -> Result<(), (RuntimeError, Loc)> {
+        for (token, loc) in tokens.iter().rev() {
+            self.token(token, loc)?;
+        }
+
+        Ok(())
+    }
+
+    fn token(&mut self, token: &Token, loc: &Loc) -> Result<(), (RuntimeError, Loc)> {
+        let table: &'static HashMap<char, Builtin> = &BUILTINS;
+
+        let instr = match token {
+            Token::Integer(n) => self.constant(Value::Integer(n.clone())),
+            Token::Rational(r) => self.constant(Value::Rational(r.clone())),
+            Token::Complex(c) => self.constant(Value::Complex(c.clone())),
+            Token::Infinity => self.constant(Value::Infinity(1)),
+            Token::Epsilon => self.constant(Value::Epsilon(1)),
+            Token::Pi(r) => self.constant(Value::Pi(r.clone(), 1)),
+            Token::E(r, pow) => self.constant(Value::E(r.clone(), *pow)),
+            Token::List(vals) => self.constant(Value::List(
+                vals.iter().map(|tok| tok.clone().into()).collect(),
+            )),
+
+            Token::Scope(body) => Instr::Scope(Rc::new(compile(body, self.builtins)?)),
+            Token::Tuple(body) => Instr::Tuple(Rc::new(compile(body, self.builtins)?)),
+
+            Token::Dup => Instr::Dup,
+            Token::Pop => Instr::Pop,
+            Token::Flip => Instr::Builtin(&table[&'↕']),
+            Token::Minus => Instr::Minus,
+
+            Token::FunctionCall(c) => match (self.builtins.get(c), table.get(c)) {
+                (Some(custom), _) => Instr::Custom(custom.clone()),
+                (None, Some(builtin)) => Instr::Builtin(builtin),
+                (None, None) => Instr::Call(*c),
+            },
+            Token::Function(body) => return self.tokens(body),
+            Token::Inverse(tok, loc) => return self.inverse(tok, loc),
+            Token::RightInverse(c) => Instr::RightInverse(&table[c]),
+            // Ran from right to left: `f`, then `g`, then the inverse of `f`
+            Token::Under(f, g) => {
+                self.token(&f.0, &f.1)?;
+                self.token(&g.0, &g.1)?;
+                return self.inverse(&f.0, &f.1);
+            }
+
+            // Already registered before running
+            Token::Definition(..) | Token::Spacing => return Ok(()),
+            Token::_UnfinishedList(_) => unreachable!("Unfinished list"),
+            Token::_Arrow => unreachable!("Unfinished definition"),
+            Token::InvalidState => unreachable!(),
+        };
+
+        self.program.code.push(instr);
+        self.program.spans.push(loc.clone());
+        Ok(())
+    }
+
+    /// Lowers the inverse of `token`, see `Env::invert`
+    fn inverse(&mut self, token: &Token, loc: &Loc) -> Result<(), (RuntimeError, Loc)> {
+        let table: &'static HashMap<char, Builtin> = &BUILTINS;
+
+        let instr = match token {
+            Token::FunctionCall(c) => match (self.builtins.get(c), table.get(c)) {
+                (Some(custom), _) if custom.inverse.is_some() => {
+                    Instr::CustomInverse(custom.clone())
+                }
+                (None, Some(builtin)) if builtin.left_inverse().is_some() => {
+                    Instr::Inverse(builtin)
+                }
+                (None, None) => Instr::CallInverse(*c),
+                _ => return Err((RuntimeError::NoInverseOf(c.to_string()), loc.clone())),
+            },
+            Token::Inverse(tok, loc) => return self.token(tok, loc),
+            Token::Function(_) | Token::Under(..) | Token::Flip | Token::Minus => {
+                let inverse = Env::invert(self.builtins, &[(token.clone(), loc.clone())])?;
+                return self.tokens(&inverse);
+            }
+            _ => return Err((RuntimeError::InverseOfNonFunction, loc.clone())),
+        };
+
+        self.program.code.push(instr);
+        self.program.spans.push(loc.clone());
+        Ok(())
+    }
+
+    fn constant(&mut self, value: Value) -> Instr {
+        self.program.constants.push(value);
+        Instr::Push(self.program.constants.len() - 1)
+    }
+}
@@ -0,0 +1,230 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    builtins::{Builtin, CustomBuiltin, Inverse, Signature, BUILTINS},
+    err::CheckError,
+    parser::{Loc, Token},
+    vm::{Env, UserFunction},
+};
+
+/// Checks that no function of `tokens` would take more values than the stack
+/// has, if ran in `env`.
+///
+/// Stops quietly once the stack's size can't be known anymore, like after
+/// `⊔` or a recursive function, the rest is left for the runtime.
+///
+/// ## Examples
+///
+/// ```text
+/// +1       # ok with a value on the stack
+/// + ×2 3   # `+` underflows, `×` leaves a single value
+/// ```
+pub fn check(tokens: &[(Token, Loc)], env: &Env) -> Result<(), (CheckError, Loc)> {
+    let mut functions = env.functions.clone();
+    Env::define(&mut functions, tokens);
+
+    Checker {
+        functions,
+        builtins: &env.builtins,
+        visiting: vec![],
+    }
+    .walk(tokens, env.stack.len())?;
+
+    Ok(())
+}
+
+struct Checker<'a> {
+    functions: HashMap<char, UserFunction>,
+    builtins: &'a HashMap<char, Rc<CustomBuiltin>>,
+    /// User functions whose signature is being inferred, to stop on recursion
+    visiting: Vec<char>,
+}
+
+impl Checker<'_> {
+    /// Goes through `tokens` the way they are ran, giving back the size of the
+    /// stack after them, if known
+    fn walk(
+        &mut self,
+        tokens: &[(Token, Loc)],
+        mut depth: usize,
+    ) -> Result<Option<usize>, (CheckError, Loc)> {
+        for (token, loc) in tokens.iter().rev() {
+            match token {
+                // Ran in place, so errors point at the step itself
+                Token::Function(body) => match self.walk(body, depth)? {
+                    Some(after) => depth = after,
+                    None => return Ok(None),
+                },
+                // Ran on an empty stack
+                Token::Tuple(body) => {
+                    self.walk(body, 0)?;
+                    depth += 1;
+                }
+                _ => {
+                    let Some(signature) = self.signature(token) else {
+                        return Ok(None);
+                    };
+                    if signature.inputs > depth {
+                        return Err((
+                            CheckError::StackUnderflow {
+                                len: depth,
+                                arity: signature.inputs,
+                            },
+                            loc.clone(),
+                        ));
+                    }
+
+                    match signature.outputs {
+                        Some(outputs) => depth = depth - signature.inputs + outputs,
+                        None => return Ok(None),
+                    }
+                }
+            }
+        }
+
+        Ok(Some(depth))
+    }
+
+    /// `None` when not even the number of inputs is known
+    fn signature(&mut self, token: &Token) -> Option<Signature> {
+        match token {
+            Token::Integer(_)
+            | Token::Rational(_)
+            | Token::Complex(_)
+            | Token::Infinity
+            | Token::Epsilon
+            | Token::Pi(_)
+            | Token::E(..)
+            | Token::List(_)
+            | Token::Tuple(_) => Some(Signature::new(0, 1)),
+            // A list is replaced by what its body leaves
+            Token::Scope(_) => Some(Signature::variable(1)),
+
+            Token::Dup => Some(Signature::new(1, 2)),
+            Token::Pop => Some(Signature::new(1, 0)),
+            Token::Flip => Some(Signature::new(2, 2)),
+            Token::Minus => Some(Signature::new(1, 1)),
+
+            Token::FunctionCall(c) if self.builtins.contains_key(c) => {
+                Some(self.builtins[c].signature)
+            }
+            Token::FunctionCall(c) => match BUILTINS.get(c) {
+                Some(builtin) => Some(builtin.signature),
+                None => {
+                    let body = self.functions.get(c)?.body.clone()?;
+                    self.inferred(*c, &body)
+                }
+            },
+            Token::Function(body) => self.effect(body),
+            Token::Inverse(tok, _) => self.inverse_signature(tok),
+            Token::RightInverse(c) => Some(BUILTINS.get(c)?.signature),
+            // Ran as `f`, then `g`, then the inverse of `f`
+            Token::Under(f, g) => {
+                let steps = [
+                    self.signature(&f.0),
+                    self.signature(&g.0),
+                    self.inverse_signature(&f.0),
+                ];
+                compose(steps)
+            }
+
+            Token::Definition(..) | Token::Spacing => Some(Signature::new(0, 0)),
+            Token::_UnfinishedList(_) | Token::_Arrow | Token::InvalidState => None,
+        }
+    }
+
+    /// An inverse takes what the function leaves and gives back what it took,
+    /// except for binary builtins, which are also given their known operand
+    ///
+    /// `None` for what can't be inverted, which is left for the runtime to
+    /// report
+    fn inverse_signature(&mut self, token: &Token) -> Option<Signature> {
+        match token {
+            Token::FunctionCall(c) if self.builtins.contains_key(c) => match &self.builtins[c] {
+                builtin if builtin.inverse.is_some() => swapped(builtin.signature),
+                _ => None,
+            },
+            Token::FunctionCall(c) => match BUILTINS.get(c) {
+                Some(builtin) if builtin.left_inverse().is_none() => None,
+                Some(
+                    builtin @ Builtin {
+                        inverse: Inverse::Binary(..),
+                        ..
+                    },
+                ) => Some(builtin.signature),
+                Some(builtin) => swapped(builtin.signature),
+                None => match self.functions.get(c)?.clone() {
+                    UserFunction {
+                        inverse: Some(inverse),
+                        ..
+                    } => self.inferred(*c, &inverse),
+                    UserFunction {
+                        body: Some(body), ..
+                    } => swapped(self.inferred(*c, &body)?),
+                    _ => None,
+                },
+            },
+            Token::Inverse(tok, _) => self.signature(tok),
+            // Undone step by step, see `Env::invert`
+            Token::Function(body) => {
+                let inverse = Env::invert(self.builtins, body).ok()?;
+                self.effect(&inverse)
+            }
+            // The inverse of `g` under `f`
+            Token::Under(f, g) => compose([
+                self.signature(&f.0),
+                self.inverse_signature(&g.0),
+                self.inverse_signature(&f.0),
+            ]),
+            // Their own inverses
+            Token::Flip | Token::Minus => self.signature(token),
+            _ => None,
+        }
+    }
+
+    /// The signature of a user function's body, unknown when it calls itself
+    fn inferred(&mut self, name: char, body: &[(Token, Loc)]) -> Option<Signature> {
+        if self.visiting.contains(&name) {
+            return None;
+        }
+
+        self.visiting.push(name);
+        let signature = self.effect(body);
+        self.visiting.pop();
+
+        signature
+    }
+
+    fn effect(&mut self, body: &[(Token, Loc)]) -> Option<Signature> {
+        let steps = body
+            .iter()
+            .rev()
+            .map(|(token, _)| self.signature(token))
+            .collect::<Vec<_>>();
+
+        compose(steps)
+    }
+}
+
+/// The signature of running `steps` one after the other, it takes as many
+/// values as the stack ever goes below where it started.
+fn compose(steps: impl IntoIterator<Item = Option<Signature>>) -> Option<Signature> {
+    let mut depth = 0isize;
+    let mut lowest = 0isize;
+
+    for step in steps {
+        let step = step?;
+        depth -= step.inputs as isize;
+        lowest = lowest.min(depth);
+        depth += step.outputs? as isize;
+    }
+
+    Some(Signature::new(
+        (-lowest) as usize,
+        (depth - lowest) as usize,
+    ))
+}
+
+fn swapped(signature: Signature) -> Option<Signature> {
+    Some(Signature::new(signature.outputs?, signature.inputs))
+}
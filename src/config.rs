@@ -0,0 +1,202 @@
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    builtins::BUILTINS,
+    formatter::{Formatter, SYMBOLS},
+};
+
+/// # Config
+///
+/// What users set in `$XDG_CONFIG_HOME/kuhi/config.toml`, or
+/// `~/.config/kuhi/config.toml`, see `Config::path`.
+///
+/// `aliases` are ASCII names for glyphs, or for snippets of many tokens,
+/// formatted along with those of `formatter::SYMBOLS`. Those that can't be
+/// used are left out and kept in `conflicts`, for the REPL to report, see
+/// `Conflict`. So formatting twice still changes nothing.
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::{config::Config, formatter::Formatter};
+///
+/// let config = Config::parse(r#"
+///     [aliases]
+///     double = "ð"
+///     square = "pow 2"
+///     sqrt = "∛"
+///     inc = "inc 1"
+/// "#).unwrap();
+///
+/// assert_eq!(config.conflicts.len(), 2);
+/// let formatted = Formatter::new("square sqrt 4".to_owned())
+///     .with_snippets(&config.aliases)
+///     .format();
+/// assert_eq!(formatted, "ⁿ 2 √ 4");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub aliases: Vec<(String, String)>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// An alias that was left out, see `Config`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// Already the name of a glyph, which is kept
+    Builtin { name: String, glyph: String },
+    /// Not ASCII, empty, or with spaces, `#` or `"` in it, which the
+    /// formatter never reads as a name
+    InvalidName(String),
+    /// Given something else than a string
+    NotAString(String),
+    /// With a character that already means something on its own, like `+`
+    /// or `1`, every use of which it would take over
+    Taken { name: String, glyph: char },
+    /// Used in its own snippet, which would grow each time it is formatted
+    Recursive(String),
+    /// With a snippet using another alias, which would only be replaced the
+    /// next time it is formatted
+    Chained { name: String, other: String },
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Builtin { name, glyph } => {
+                write!(f, "alias `{name}` is already `{glyph}`, which is kept")
+            }
+            Conflict::InvalidName(name) => write!(
+                f,
+                "alias `{name}` is left out, names are ASCII without spaces, `#` or `\"`"
+            ),
+            Conflict::NotAString(name) => {
+                write!(f, "alias `{name}` is left out, it must be a string")
+            }
+            Conflict::Taken { name, glyph } => write!(
+                f,
+                "alias `{name}` is left out, `{glyph}` already means something on its own"
+            ),
+            Conflict::Recursive(name) => {
+                write!(f, "alias `{name}` is left out, its snippet uses it again")
+            }
+            Conflict::Chained { name, other } => write!(
+                f,
+                "alias `{name}` is left out, its snippet uses the alias `{other}`"
+            ),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("couldn't read `{0}`: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("invalid config: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+impl Config {
+    /// Where the config is, `None` when neither `$XDG_CONFIG_HOME` nor
+    /// `$HOME` are set
+    pub fn path() -> Option<PathBuf> {
+        let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(dir.join("kuhi").join("config.toml"))
+    }
+
+    /// The config at `Config::path`, the default one when there is none
+    pub fn load() -> Result<Self, ConfigError> {
+        match Config::path() {
+            Some(path) if path.exists() => Config::read(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let src =
+            std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.to_owned(), err))?;
+
+        Config::parse(&src)
+    }
+
+    pub fn parse(src: &str) -> Result<Self, ConfigError> {
+        let table = src.parse::<toml::Table>()?;
+        let mut config = Config::default();
+
+        let Some(aliases) = table.get("aliases").and_then(toml::Value::as_table) else {
+            return Ok(config);
+        };
+        for (name, value) in aliases {
+            let valid = !name.is_empty()
+                && name.is_ascii()
+                && !name.contains(|c: char| c.is_ascii_whitespace() || c == '#' || c == '"');
+            let builtin = SYMBOLS.iter().find(|(builtin, _)| builtin == name);
+            let taken = name.chars().find(|c| taken(*c));
+
+            let conflict = match (value.as_str(), builtin, taken) {
+                _ if !valid => Conflict::InvalidName(name.clone()),
+                (None, ..) => Conflict::NotAString(name.clone()),
+                (Some(_), _, Some(glyph)) => Conflict::Taken {
+                    name: name.clone(),
+                    glyph,
+                },
+                (Some(_), Some((_, glyph)), None) => Conflict::Builtin {
+                    name: name.clone(),
+                    glyph: glyph.to_string(),
+                },
+                (Some(text), None, None) => {
+                    config.aliases.push((name.clone(), text.to_owned()));
+                    continue;
+                }
+            };
+            config.conflicts.push(conflict);
+        }
+
+        // Snippets are formatted once, when they are loaded, so those using
+        // an alias wouldn't be formatted to the end
+        let aliases = std::mem::take(&mut config.aliases);
+        let formatter = Formatter::new(String::new()).with_snippets(&aliases);
+        for (name, text) in &aliases {
+            let snippet = Formatter::new(text.clone()).format();
+            let used = formatter
+                .read(&snippet)
+                .into_iter()
+                .find(|used| aliases.iter().any(|(other, _)| other == used));
+
+            match used {
+                Some(other) if other == name => {
+                    config.conflicts.push(Conflict::Recursive(name.clone()))
+                }
+                Some(other) => config.conflicts.push(Conflict::Chained {
+                    name: name.clone(),
+                    other: other.to_owned(),
+                }),
+                None => config.aliases.push((name.clone(), text.clone())),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Whether `c` already means something on its own, like `+`, `1` or `:`,
+/// which a name with it in would take over
+fn taken(c: char) -> bool {
+    let glyph = c.to_string();
+
+    c.is_ascii_digit()
+        || "()[]".contains(c)
+        || BUILTINS.contains_key(&c)
+        || SYMBOLS
+            .iter()
+            .any(|(name, to)| *name == glyph || *to == glyph)
+}
@@ -1,8 +1,19 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
 
 use rug::Integer;
+use serde_json::json;
 use thiserror::Error;
 
+use crate::{
+    array::Array,
+    parser::Loc,
+    suggest::{did_you_mean, word_at},
+    value::Value,
+};
+
 #[derive(Error, Debug)]
 pub enum SyntaxError {
     InvalidSymbol(char),
@@ -10,7 +21,12 @@ pub enum SyntaxError {
     UnmatchedParenthesis(bool),
     /// true if `[`, false if `]`
     UnmatchedSquareBracket(bool),
+    /// true if `⟨`, false if `⟩`
+    UnmatchedAngleBracket(bool),
     LonelyInverse,
+    LonelyUnder,
+    UnterminatedComment,
+    InvalidDefinition,
 }
 
 impl Display for SyntaxError {
@@ -19,7 +35,11 @@ impl Display for SyntaxError {
             SyntaxError::InvalidSymbol(_) => write!(f, "invalid symbol"),
             SyntaxError::UnmatchedParenthesis(_) => write!(f, "unmatched parenthesis"),
             SyntaxError::UnmatchedSquareBracket(_) => write!(f, "unmatched square bracket"),
+            SyntaxError::UnmatchedAngleBracket(_) => write!(f, "unmatched angle bracket"),
             SyntaxError::LonelyInverse => write!(f, "lonely inverse"),
+            SyntaxError::LonelyUnder => write!(f, "lonely under"),
+            SyntaxError::UnterminatedComment => write!(f, "unterminated block comment"),
+            SyntaxError::InvalidDefinition => write!(f, "invalid definition"),
         }
     }
 }
@@ -38,7 +58,34 @@ impl SyntaxError {
                 "there is a missing {} square bracket in the code",
                 if *open { "opening" } else { "closing" }
             ),
+            SyntaxError::UnmatchedAngleBracket(open) => format!(
+                "there is a missing {} angle bracket in the code",
+                if *open { "opening" } else { "closing" }
+            ),
             SyntaxError::LonelyInverse => "must have something to invert".to_owned(),
+            SyntaxError::LonelyUnder => {
+                "must be followed by two functions, like `⍜(×10)(+1)`".to_owned()
+            }
+            SyntaxError::UnterminatedComment => {
+                "there is a missing `}#` to close the comment".to_owned()
+            }
+            SyntaxError::InvalidDefinition => {
+                "definitions look like `f ← (…)`, or `⁻¹f ← (…)` for the inverse, and\ncan't be named after a builtin".to_owned()
+            }
+        }
+    }
+
+    /// Never changes nor is reused, see `explain::explain`
+    pub fn code(&self) -> &'static str {
+        match self {
+            SyntaxError::InvalidSymbol(_) => "K0001",
+            SyntaxError::UnmatchedParenthesis(_) => "K0002",
+            SyntaxError::UnmatchedSquareBracket(_) => "K0003",
+            SyntaxError::UnmatchedAngleBracket(_) => "K0004",
+            SyntaxError::LonelyInverse => "K0005",
+            SyntaxError::LonelyUnder => "K0006",
+            SyntaxError::UnterminatedComment => "K0007",
+            SyntaxError::InvalidDefinition => "K0008",
         }
     }
 }
@@ -49,6 +96,7 @@ pub enum RuntimeError {
 
     ListTypeMissmatch { first: String, second: String },
     ListElementSizeMissmatch { first: usize, second: usize },
+    ShapeMissmatch { first: Vec<usize>, second: Vec<usize> },
 
     InvalidPop { len: usize, arity: usize },
 
@@ -63,9 +111,18 @@ pub enum RuntimeError {
     DivideByZero,
 
     InvalidIotaValue,
+    IndexOutOfRange { index: Integer, len: usize },
+    InvalidSlice { start: usize, end: usize },
 
     NoInverse,
+    NoInverseOf(String),
     InverseOfNonFunction,
+
+    StepLimit(u64),
+    ElementLimit(usize),
+    BitLimit(u64),
+    Cancelled,
+    DepthLimit(usize),
 }
 
 impl Display for RuntimeError {
@@ -82,6 +139,11 @@ impl Display for RuntimeError {
                 "list has an element of size `{}` followed by one of size `{}`",
                 first, second
             ),
+            RuntimeError::ShapeMissmatch { first, second } => write!(
+                f,
+                "shapes `{:?}` and `{:?}` don't agree",
+                first, second
+            ),
             RuntimeError::InvalidPop { len, arity } => write!(
                 f,
                 "attempt to pop {} times from a stack of size {}",
@@ -103,8 +165,22 @@ impl Display for RuntimeError {
             RuntimeError::ZerothRoot => write!(f, "cannot take the 0th root"),
             RuntimeError::DivideByZero => write!(f, "cannot divide by zero"),
             RuntimeError::InvalidIotaValue => write!(f, "can only be called with positive, not too large integers"),
+            RuntimeError::IndexOutOfRange { index, len } => {
+                write!(f, "index {index} is out of range for a length of {len}")
+            }
+            RuntimeError::InvalidSlice { start, end } => {
+                write!(f, "slice starts at {start}, after it ends at {end}")
+            }
             RuntimeError::NoInverse => write!(f, "function is not inversible"),
+            RuntimeError::NoInverseOf(step) => write!(f, "step `{}` is not inversible", step),
             RuntimeError::InverseOfNonFunction => write!(f, "cannot invert a non-function"),
+            RuntimeError::StepLimit(max) => write!(f, "ran more than {} steps", max),
+            RuntimeError::ElementLimit(max) => {
+                write!(f, "value would have more than {} elements", max)
+            }
+            RuntimeError::BitLimit(max) => write!(f, "integer would be bigger than {} bits", max),
+            RuntimeError::Cancelled => write!(f, "cancelled"),
+            RuntimeError::DepthLimit(max) => write!(f, "calls nested more than {} deep", max),
         }
     }
 }
@@ -119,6 +195,9 @@ impl RuntimeError {
             RuntimeError::ListElementSizeMissmatch { .. } => {
                 format!("ensure the list has elements of the same size")
             }
+            RuntimeError::ShapeMissmatch { .. } => {
+                "one shape must start with the other, like `[2]` and `[2, 3]`".to_owned()
+            }
             RuntimeError::InvalidPop { .. } => {
                 format!("ensure you are using the correct function or add more values to the stack")
             }
@@ -134,8 +213,477 @@ impl RuntimeError {
                 "try filtering the 0s on the stack\nuse ε to produce a small number instead of 0"
             ),
             RuntimeError::InvalidIotaValue => format!("make the value positive and smaller than {} (u32::MAX)", u32::MAX),
+            RuntimeError::IndexOutOfRange { len: 0, .. } => {
+                "the list is empty, so no index is valid".to_owned()
+            }
+            RuntimeError::IndexOutOfRange { len, .. } => format!(
+                "valid indices go from 0 to {}, or from ⁻1 to ⁻{len} counting from the end",
+                len.saturating_sub(1)
+            ),
+            RuntimeError::InvalidSlice { .. } => {
+                "the start must come before the end, like `⊘1‿3`".to_owned()
+            }
             RuntimeError::NoInverse => format!("rethink your logic"),
+            RuntimeError::NoInverseOf(_) => {
+                "a function can only be inverted when all of its steps can\ngive it an inverse with `⁻¹f ← (…)`"
+                    .to_string()
+            }
             RuntimeError::InverseOfNonFunction => format!("ensure inverse comes after a function"),
+            RuntimeError::StepLimit(_) => {
+                "the program may not terminate, or raise the limit with `Limits::steps`".to_owned()
+            }
+            RuntimeError::ElementLimit(_) => {
+                "try working on smaller lists, or raise the limit with `Limits::elements`"
+                    .to_owned()
+            }
+            RuntimeError::BitLimit(_) => {
+                "try smaller numbers, or raise the limit with `Limits::bits`".to_owned()
+            }
+            RuntimeError::Cancelled => "the program was stopped before it finished".to_owned(),
+            RuntimeError::DepthLimit(_) => {
+                "a function may call itself forever, or raise the limit with `Limits::depth`"
+                    .to_owned()
+            }
+        }
+    }
+
+    /// Never changes nor is reused, see `explain::explain`
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::FunctionNotFound(_) => "K0009",
+            RuntimeError::ListTypeMissmatch { .. } => "K0010",
+            RuntimeError::ListElementSizeMissmatch { .. } => "K0011",
+            RuntimeError::ShapeMissmatch { .. } => "K0012",
+            RuntimeError::InvalidPop { .. } => "K0013",
+            RuntimeError::InvalidFoldWith(_) => "K0014",
+            RuntimeError::InvalidMapWith(_) => "K0015",
+            RuntimeError::InvalidFilterWith(_) => "K0016",
+            RuntimeError::TypeMissmatch { .. } => "K0017",
+            RuntimeError::ExponentTooBig(_) => "K0018",
+            RuntimeError::ZerothRoot => "K0019",
+            RuntimeError::DivideByZero => "K0020",
+            RuntimeError::InvalidIotaValue => "K0021",
+            RuntimeError::IndexOutOfRange { .. } => "K0022",
+            RuntimeError::InvalidSlice { .. } => "K0034",
+            RuntimeError::NoInverse => "K0023",
+            RuntimeError::NoInverseOf(_) => "K0024",
+            RuntimeError::InverseOfNonFunction => "K0025",
+            RuntimeError::StepLimit(_) => "K0026",
+            RuntimeError::ElementLimit(_) => "K0027",
+            RuntimeError::BitLimit(_) => "K0028",
+            RuntimeError::Cancelled => "K0029",
+            RuntimeError::DepthLimit(_) => "K0035",
+        }
+    }
+}
+
+/// A call a runtime error went through on its way out, see `Env::run`
+///
+/// `loc` is where the call is, `repeated` how many times in a row the same
+/// call was made there, like in a recursive function
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub loc: Loc,
+    pub repeated: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    Function(char),
+    Inverse(char),
+    Scope,
+    Tuple,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            FrameKind::Function(c) => write!(f, "in `{}`, called here", c)?,
+            FrameKind::Inverse(c) => write!(f, "in the inverse of `{}`, called here", c)?,
+            FrameKind::Scope => write!(f, "in this scope")?,
+            FrameKind::Tuple => write!(f, "in this tuple")?,
+        }
+        if self.repeated > 1 {
+            write!(f, " ({} times)", self.repeated)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A value on the stack right before the step a runtime error happened in
+///
+/// Lists and every axis of arrays are cut to their first
+/// `StackValue::PREVIEW` elements, `len` being how many there were at the top
+#[derive(Clone, Debug)]
+pub struct StackValue {
+    pub value: Value,
+    pub len: Option<usize>,
+}
+
+impl StackValue {
+    pub const PREVIEW: usize = 8;
+
+    pub fn new(value: &Value) -> Self {
+        Self {
+            value: StackValue::preview(value),
+            len: StackValue::len(value),
+        }
+    }
+
+    /// Takes `value` in place of the one kept, reusing what it allocated, so
+    /// the VM can keep what each builtin is given without allocating anew
+    pub(crate) fn retake(&mut self, value: &Value) {
+        self.len = StackValue::len(value);
+        StackValue::preview_into(&mut self.value, value);
+    }
+
+    pub fn types(&self) -> Vec<String> {
+        self.value.types()
+    }
+
+    fn len(value: &Value) -> Option<usize> {
+        let len = match value {
+            Value::List(vals) | Value::Tuple(vals) => vals.len(),
+            Value::Array(array) => array.shape.first().copied().unwrap_or(1),
+            _ => 0,
+        };
+
+        (len > StackValue::PREVIEW).then_some(len)
+    }
+
+    fn preview(value: &Value) -> Value {
+        let n = StackValue::PREVIEW;
+        match value {
+            Value::List(vals) => Value::List(vals.iter().take(n).map(StackValue::preview).collect()),
+            Value::Tuple(vals) => Value::Tuple(vals.iter().take(n).map(StackValue::preview).collect()),
+            Value::Array(array) if array.shape.iter().any(|len| *len > n) => {
+                let shape: Vec<usize> = array.shape.iter().map(|len| (*len).min(n)).collect();
+
+                // Walking the kept indices, the last axis the fastest
+                let mut index = vec![0; shape.len()];
+                let mut data = Vec::with_capacity(shape.iter().product());
+                for _ in 0..shape.iter().product::<usize>() {
+                    let at = index.iter().zip(&array.shape).fold(0, |at, (i, len)| at * len + i);
+                    data.push(StackValue::preview(&array.data[at]));
+
+                    for (i, len) in index.iter_mut().zip(&shape).rev() {
+                        *i += 1;
+                        if *i < *len {
+                            break;
+                        }
+                        *i = 0;
+                    }
+                }
+                Value::Array(Array::new(shape, data))
+            }
+            _ => value.clone(),
+        }
+    }
+
+    /// Like `preview`, writing into `into` instead of a new value
+    fn preview_into(into: &mut Value, value: &Value) {
+        let n = StackValue::PREVIEW;
+        match (into, value) {
+            (Value::List(into), Value::List(vals)) | (Value::Tuple(into), Value::Tuple(vals)) => {
+                into.truncate(vals.len().min(n));
+                for (i, val) in vals.iter().take(n).enumerate() {
+                    match into.get_mut(i) {
+                        Some(into) => StackValue::preview_into(into, val),
+                        None => into.push(StackValue::preview(val)),
+                    }
+                }
+            }
+            (Value::Integer(into), Value::Integer(x)) => into.clone_from(x),
+            (Value::Rational(into), Value::Rational(x)) => into.clone_from(x),
+            (Value::Float(into), Value::Float(x)) => into.clone_from(x),
+            (into, value) => *into = StackValue::preview(value),
+        }
+    }
+}
+
+impl Display for StackValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.len {
+            Some(len) => write!(f, "{}, the first {} of {}", self.value, StackValue::PREVIEW, len),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// What a runtime error went through, see `Env::run`
+///
+/// `trace` has the calls it happened in, innermost first \
+/// `stack` has the top of the stack right before the step that failed, the
+/// top last
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub trace: Vec<Frame>,
+    pub stack: Vec<StackValue>,
+}
+
+/// Found before running, by `check::check`
+#[derive(Clone, Debug)]
+pub enum CheckError {
+    StackUnderflow { len: usize, arity: usize },
+}
+
+impl Display for CheckError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::StackUnderflow { len, arity } => write!(
+                f,
+                "stack underflow, takes {} values but the stack would only have {}",
+                arity, len
+            ),
+        }
+    }
+}
+
+impl CheckError {
+    pub fn note(&self) -> String {
+        match self {
+            CheckError::StackUnderflow { .. } => {
+                "nothing was ran, add more values to the stack before this point".to_owned()
+            }
+        }
+    }
+
+    /// Never changes nor is reused, see `explain::explain`
+    pub fn code(&self) -> &'static str {
+        match self {
+            CheckError::StackUnderflow { .. } => "K0030",
         }
     }
 }
+
+/// Found while running, when enabled with `Env::warnings`, the program
+/// still running to the end
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// Exact values giving a float, like `π` added to an integer
+    Inexact,
+    /// `Undefined` out of values that weren't
+    Undefined,
+    /// Infinities, or epsilons, of opposite signs, or an infinity and an
+    /// epsilon, giving `Undefined`
+    Cancellation,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Inexact => write!(f, "exact values gave an approximation"),
+            Warning::Undefined => write!(f, "gave an undefined value"),
+            Warning::Cancellation => write!(f, "infinities and epsilons cancelled out"),
+        }
+    }
+}
+
+impl Warning {
+    pub fn note(&self) -> String {
+        match self {
+            Warning::Inexact => {
+                "the result is a 128 bit float, it isn't exact anymore".to_owned()
+            }
+            Warning::Undefined => "everything computed from it is undefined too".to_owned(),
+            Warning::Cancellation => {
+                "they are only known by their sign, so the result is undefined".to_owned()
+            }
+        }
+    }
+
+    /// Never changes nor is reused, see `explain::explain`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::Inexact => "K0031",
+            Warning::Undefined => "K0032",
+            Warning::Cancellation => "K0033",
+        }
+    }
+}
+
+/// Any error from evaluating source, see `Diagnostic`
+#[derive(Debug)]
+pub enum ErrorKind {
+    Syntax(SyntaxError),
+    Check(CheckError),
+    Runtime(RuntimeError),
+    /// Not an error, the source did run
+    Warning(Warning),
+}
+
+/// An error, or a warning, and where it happened in the source
+///
+/// `loc` and `span` point into everything given to the `Interpreter` so far,
+/// which is what `Interpreter::source` gives back. `span` is in bytes, up to
+/// the end of the last character
+///
+/// `trace` and `stack` are those of the runtime error's `Context`, with the
+/// span of each call
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub kind: ErrorKind,
+    pub loc: Loc,
+    pub span: Range<usize>,
+    pub trace: Vec<(Frame, Range<usize>)>,
+    pub stack: Vec<StackValue>,
+    /// What was meant instead, see `suggest::did_you_mean`
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: ErrorKind, loc: Loc, source: &str) -> Self {
+        let help = match kind {
+            ErrorKind::Runtime(RuntimeError::FunctionNotFound(glyph)) => {
+                did_you_mean(glyph, word_at(source, loc.start))
+            }
+            _ => None,
+        };
+
+        Self {
+            kind,
+            span: span(&loc, source),
+            loc,
+            trace: vec![],
+            stack: vec![],
+            help,
+        }
+    }
+
+    pub fn with_context(mut self, context: Context, source: &str) -> Self {
+        self.stack = context.stack;
+        self.trace = context
+            .trace
+            .into_iter()
+            .map(|frame| {
+                let span = span(&frame.loc, source);
+                (frame, span)
+            })
+            .collect();
+
+        self
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.kind {
+            ErrorKind::Syntax(_) => "Syntax error",
+            ErrorKind::Check(_) => "Check error",
+            ErrorKind::Runtime(_) => "Runtime error",
+            ErrorKind::Warning(_) => "Warning",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ErrorKind::Syntax(err) => err.to_string(),
+            ErrorKind::Check(err) => err.to_string(),
+            ErrorKind::Runtime(err) => err.to_string(),
+            ErrorKind::Warning(warning) => warning.to_string(),
+        }
+    }
+
+    pub fn note(&self) -> String {
+        match &self.kind {
+            ErrorKind::Syntax(err) => err.note(),
+            ErrorKind::Check(err) => err.note(),
+            ErrorKind::Runtime(err) => err.note(),
+            ErrorKind::Warning(warning) => warning.note(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match &self.kind {
+            ErrorKind::Syntax(err) => err.code(),
+            ErrorKind::Check(err) => err.code(),
+            ErrorKind::Runtime(err) => err.code(),
+            ErrorKind::Warning(warning) => warning.code(),
+        }
+    }
+
+    /// # JSON
+    ///
+    /// For tools reading errors, `span` being in bytes and `line` and
+    /// `column` counting from 1, see `Diagnostic`.
+    ///
+    /// ```json
+    /// {
+    ///   "kind": "runtime",
+    ///   "code": "K0017",
+    ///   "message": "expected type `Integer`, got `Tuple`",
+    ///   "note": "ensure the function you're using works for …",
+    ///   "help": null,
+    ///   "span": { "start": 0, "end": 2 },
+    ///   "line": 1,
+    ///   "column": 1,
+    ///   "trace": [
+    ///     { "message": "in `f`, called here", "span": { … }, "line": 2, "column": 1 }
+    ///   ],
+    ///   "stack": [{ "value": "⟨1⟩", "types": ["Tuple"] }]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        let kind = match self.kind {
+            ErrorKind::Syntax(_) => "syntax",
+            ErrorKind::Check(_) => "check",
+            ErrorKind::Runtime(_) => "runtime",
+            ErrorKind::Warning(_) => "warning",
+        };
+        let trace = self
+            .trace
+            .iter()
+            .map(|(frame, span)| {
+                json!({
+                    "message": frame.to_string(),
+                    "span": { "start": span.start, "end": span.end },
+                    "line": frame.loc.line,
+                    "column": frame.loc.column,
+                })
+            })
+            .collect::<Vec<_>>();
+        let stack = self
+            .stack
+            .iter()
+            .map(|value| json!({ "value": value.to_string(), "types": value.types() }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "kind": kind,
+            "code": self.code(),
+            "message": self.message(),
+            "note": self.note(),
+            "help": self.help,
+            "span": { "start": self.span.start, "end": self.span.end },
+            "line": self.loc.line,
+            "column": self.loc.column,
+            "trace": trace,
+            "stack": stack,
+        })
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}: {}",
+            self.title(),
+            self.loc.line,
+            self.loc.column,
+            self.message()
+        )
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// The bytes of `source` that `loc` covers
+fn span(loc: &Loc, source: &str) -> Range<usize> {
+    let end = source
+        .get(loc.end..)
+        .and_then(|rest| rest.chars().next())
+        .map_or(loc.end + 1, |c| loc.end + c.len_utf8());
+
+    loc.start..end
+}
@@ -0,0 +1,447 @@
+/// # Explanations
+///
+/// The long form of every error code, what `kuhi --explain K0017` prints.
+/// Codes are never reused, a removed error keeps its code.
+///
+/// | Codes         | Errors         |
+/// |---------------|----------------|
+/// | K0001 - K0008 | `SyntaxError`  |
+/// | K0009 - K0029 | `RuntimeError` |
+/// | K0030         | `CheckError`   |
+/// | K0031 - K0033 | `Warning`      |
+/// | K0034 - K0035 | `RuntimeError` |
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::explain::explain;
+///
+/// assert!(explain("K0017").unwrap().starts_with("K0017"));
+/// assert!(explain("K9999").is_none());
+/// ```
+pub fn explain(code: &str) -> Option<&'static str> {
+    let explanation = match code {
+        "K0001" => {
+            "K0001: invalid symbol
+
+A character that means nothing in kuhi was found in the source.
+
+Erroneous example:
+
+    +1 $ 2      # `$` is not a builtin
+
+ASCII spellings, like `sqrt` for `√`, are only turned into their glyph by
+the REPL. Check the docs for the list of builtins, or define the function
+with `f ← (…)`."
+        }
+        "K0002" => {
+            "K0002: unmatched parenthesis
+
+A `(` was never closed, or a `)` was never opened.
+
+Erroneous example:
+
+    f ← (+1 ×2      # missing `)`
+
+Every function body needs both its parentheses:
+
+    f ← (+1 ×2)"
+        }
+        "K0003" => {
+            "K0003: unmatched square bracket
+
+A `[` was never closed, or a `]` was never opened. Square brackets open a
+scope, which runs on the values of the list on top of the stack.
+
+Erroneous example:
+
+    [+1 1‿2      # missing `]`"
+        }
+        "K0004" => {
+            "K0004: unmatched angle bracket
+
+A `⟨` was never closed, or a `⟩` was never opened. Angle brackets build a
+tuple from what their body leaves on an empty stack.
+
+Erroneous example:
+
+    ⟨1 2      # missing `⟩`
+
+Close the tuple:
+
+    ⟨1 2⟩"
+        }
+        "K0005" => {
+            "K0005: lonely inverse
+
+`⁻¹` was not followed by what to invert.
+
+Erroneous example:
+
+    ⁻¹
+
+It goes right before a function, like `⁻¹ⁿ2 9` which is `3`."
+        }
+        "K0006" => {
+            "K0006: lonely under
+
+`⍜` was not followed by the two functions it takes.
+
+Erroneous example:
+
+    ⍜(×10)
+
+`⍜f g` runs `f`, then `g`, then the inverse of `f`:
+
+    ⍜(×10)(+1) 2      # 21/10"
+        }
+        "K0007" => {
+            "K0007: unterminated block comment
+
+A block comment was opened with `#{` but never closed with `}#`.
+
+Erroneous example:
+
+    +1 2 #{ adds one
+
+Close the comment, or use `#` for a comment running to the end of the line:
+
+    +1 2 #{ adds one }#
+    +1 2 # adds one"
+        }
+        "K0008" => {
+            "K0008: invalid definition
+
+`←` was not used as `f ← (…)`, a single glyph on its left and a function
+body in parentheses on its right.
+
+Erroneous example:
+
+    f ← 1
+
+Put the body in parentheses, even when it only pushes a value:
+
+    f ← (1)
+
+The inverse of a function is given with `⁻¹f ← (…)`.
+
+Nor can the glyph be a builtin's, which is always called instead:
+
+    ι ← (+1)"
+        }
+        "K0009" => {
+            "K0009: function not found
+
+A glyph was called that is neither a builtin nor a defined function.
+
+Erroneous example:
+
+    f 1      # `f` was never defined
+
+Define it first:
+
+    f ← (+1)
+    f 1
+
+When the glyph looks like a builtin, or is part of a misspelled ASCII name
+like `sqr`, the error suggests what may have been meant."
+        }
+        "K0010" => {
+            "K0010: list type mismatch
+
+A list was built with elements of different types. Lists hold values of a
+single type, tuples can mix them.
+
+Use a tuple, like `⟨1 1‿2⟩`, to keep values of different types together."
+        }
+        "K0011" => {
+            "K0011: list element size mismatch
+
+Two lists were combined element by element, but their lengths differ.
+
+Erroneous example:
+
+    + 1‿2 1‿2‿3
+
+Both lists need as many elements:
+
+    + 1‿2‿3 1‿2‿3      # [2 4 6]"
+        }
+        "K0012" => {
+            "K0012: shape mismatch
+
+Two arrays were combined, but neither shape starts with the other, so one
+can't be spread over the other.
+
+Erroneous example:
+
+    + ↯2‿2 ι4 ι3      # shapes [3] and [2, 2]
+
+A shape `[2]` is spread over each row of a shape `[2, 3]`, but `[3]` can't be
+spread over `[2, 2]`. Reshape or take from one of them first."
+        }
+        "K0013" => {
+            "K0013: invalid pop
+
+A function took more values than there were on the stack. Most of these are
+found before running, as K0030, this one happens when a function leaves a
+number of values that can't be known beforehand, like `⊔`.
+
+Add more values to the stack, or check that the right function is used."
+        }
+        "K0014" => {
+            "K0014: invalid fold
+
+A fold was given a function that doesn't take two values. Folding combines
+the elements of a list two by two, so only binary functions can be used,
+like `+` or `×`."
+        }
+        "K0015" => {
+            "K0015: invalid map
+
+A map was given a function that doesn't take a single value. Mapping calls
+the function on every element of a list, so only unary functions can be
+used, like `√` or `ι`."
+        }
+        "K0016" => {
+            "K0016: invalid filter
+
+A filter was given a function that doesn't take a single value. Filtering
+calls the function on every element of a list to tell whether to keep it,
+so only unary functions can be used."
+        }
+        "K0017" => {
+            "K0017: type mismatch
+
+A function was given a value of a type it doesn't work on.
+
+Erroneous example:
+
+    ι ⟨1⟩      # `ι` takes an integer, not a tuple
+
+The error shows the types it got, and the diagnostic shows what was on the
+stack. Types are written from the most general, like `Number ⊂ Integer`."
+        }
+        "K0018" => {
+            "K0018: exponent too big
+
+An exact number was raised to a power that doesn't fit in 32 bits.
+
+Erroneous example:
+
+    ⁿ5000000000 1
+
+Exact results that big can't be computed, the exponent must be at most
+4294967295 (u32::MAX)."
+        }
+        "K0019" => {
+            "K0019: zeroth root
+
+The 0th root of a number was taken, which isn't defined.
+
+Erroneous example:
+
+    √0 4
+
+Filter the 0s out of the stack before taking roots."
+        }
+        "K0020" => {
+            "K0020: divide by zero
+
+An exact number was divided by 0.
+
+Filter the 0s out of the stack before dividing, or divide by `ε`, a number
+too small to be written, to get an infinity instead."
+        }
+        "K0021" => {
+            "K0021: invalid iota value
+
+`ι` was given an integer that is not positive, or too large for a list.
+
+Erroneous example:
+
+    ι0
+
+`ιn` is the list from 1 to n, so n must be at least 1:
+
+    ι3      # [1 2 3]"
+        }
+        "K0022" => {
+            "K0022: index out of range
+
+An index was past the end of a list.
+
+Erroneous example:
+
+    ⊡5 1‿2
+
+Indices go from 0 to the length minus 1, or from ⁻1 to minus the length to
+count from the end:
+
+    ⊡⁻1 1‿2      # 2"
+        }
+        "K0023" => {
+            "K0023: no inverse
+
+A function that can't be undone was inverted. Some functions lose what
+they were given, like `⊡` which keeps a single element, so there is no way
+back. Give the function an inverse with `⁻¹f ← (…)` when you know one."
+        }
+        "K0024" => {
+            "K0024: step is not inversible
+
+A function was inverted, but one of its steps can't be.
+
+Erroneous example:
+
+    ⁻¹(⊡1) 3
+
+A function is inverted by undoing its steps in reverse order, so all of
+them need an inverse. Give the function one yourself:
+
+    f ← (+1 ×2)
+    ⁻¹f ← (÷2 -1)"
+        }
+        "K0025" => {
+            "K0025: inverse of a non-function
+
+`⁻¹` was put before a value rather than a function.
+
+Erroneous example:
+
+    ⁻¹1 2
+
+Only functions can be inverted, like `⁻¹+1 2` which is `1`."
+        }
+        "K0026" => {
+            "K0026: step limit
+
+The program ran more steps than allowed, it may never terminate.
+
+Erroneous example:
+
+    f ← (+1 f)      # calls itself forever
+    f 0
+
+Give recursive functions a way to stop, or raise the limit with
+`Limits::steps` when embedding kuhi."
+        }
+        "K0027" => {
+            "K0027: element limit
+
+A value would have had more elements than allowed, counting those of nested
+lists.
+
+Erroneous example:
+
+    ι1000000000
+
+Work on smaller lists, or raise the limit with `Limits::elements` when
+embedding kuhi."
+        }
+        "K0028" => {
+            "K0028: bit limit
+
+An exact integer would have been bigger than allowed.
+
+Erroneous example:
+
+    ⁿ100000000000 2
+
+Use smaller numbers, or raise the limit with `Limits::bits` when embedding
+kuhi."
+        }
+        "K0029" => {
+            "K0029: cancelled
+
+The program was stopped before it finished, by Ctrl-C in the REPL or by
+the host setting `Interpreter::canceller`. Nothing it did was kept."
+        }
+        "K0030" => {
+            "K0030: stack underflow
+
+A function would take more values than the stack would have. This is found
+before running, so nothing was ran.
+
+Erroneous example:
+
+    +1 +1      # the second `+` only has 1 value
+
+Add the missing values to the stack:
+
+    +1 +1 2"
+        }
+        "K0031" => {
+            "K0031: exact values gave an approximation
+
+A step was given only exact values, like integers, rationals or multiples of
+`π`, but gave a float, which is rounded to 128 bits.
+
+Example:
+
+    + π 1      # 4.141592653589793
+
+Some results can't be exact, like `◯1`, but some can stay exact when written
+differently, like `×π 2` which is `2π`. This is only a warning, the program
+still ran."
+        }
+        "K0032" => {
+            "K0032: gave an undefined value
+
+A step gave `undef` out of values that weren't.
+
+Example:
+
+    × ∞ 0
+
+`undef` spreads to everything computed from it, so it is best caught where it
+first appears. This is only a warning, the program still ran."
+        }
+        "K0033" => {
+            "K0033: infinities and epsilons cancelled out
+
+Infinities and epsilons are only known by their sign, so when they cancel
+out, nothing is left to know the result by and it is `undef`.
+
+Example:
+
+    + ∞ ⁻∞
+    × ∞ ε
+
+This is only a warning, the program still ran."
+        }
+        "K0034" => {
+            "K0034: invalid slice
+
+A slice started after its end, once indices counting from the end were
+turned into ones counting from the start.
+
+Erroneous example:
+
+    ⊘3‿1 ι5
+    ⊘⁻1‿2 ι5      # from 4 to 2
+
+The start comes first, and the end isn't included:
+
+    ⊘1‿3 ι5       # [2 3]
+    ⊘⁻2‿5 ι5      # [4 5]"
+        }
+        "K0035" => {
+            "K0035: depth limit
+
+Calls nested deeper than allowed, those of functions, scopes and tuples
+alike. Each one takes some of the native stack, which would run out.
+
+Erroneous example:
+
+    f ← (f +1)      # calls itself before returning
+    f 0
+
+Give recursive functions a way to stop, or raise the limit with
+`Limits::depth` when embedding kuhi."
+        }
+        _ => return None,
+    };
+
+    Some(explanation)
+}
@@ -1,84 +1,337 @@
-use std::collections::HashMap;
+use crate::parser::{skip_comment, Loc};
 
+/// The ASCII spellings of glyphs, like `sqrt` for `√`
+pub const SYMBOLS: &[(&str, &str)] = &[
+    ("_", "‿"),
+    ("{", "⟨"),
+    ("}", "⟩"),
+    ("infinity", "∞"),
+    ("epsilon", "ε"),
+    ("pi", "π"),
+    ("tau", "τ"),
+    ("alpha", "α"),
+    ("iota", "ι"),
+    (":", "↕"),
+    ("flip", "↕"),
+    ("swap", "↔"),
+    ("roll", "↺"),
+    ("`", "⁻"),
+    ("*", "×"),
+    ("%", "÷"),
+    ("pow", "ⁿ"),
+    ("log", "ₙ"),
+    ("croot", "∛"),
+    ("cbrt", "∛"),
+    ("sqrt", "√"),
+    ("root", "√"),
+    ("sin", "◯"),
+    ("sinh", "ⓔ"),
+    ("sins", "Ⓞ"),
+    ("inverse", "⁻¹"),
+    ("<-", "←"),
+    ("under", "⍜"),
+    ("digits", "⊤"),
+    ("undigits", "⊥"),
+    ("unpack", "⊔"),
+    ("pick", "⊡"),
+    ("select", "⊏"),
+    ("take", "↑"),
+    ("drop", "↓"),
+    ("slice", "⊘"),
+    ("first", "⊢"),
+    ("last", "⊣"),
+    ("sortup", "∧"),
+    ("sortdown", "∨"),
+    ("gradeup", "⍋"),
+    ("gradedown", "⍒"),
+    ("search", "⍸"),
+    ("indexof", "⊐"),
+    ("member", "∊"),
+    ("unique", "⍷"),
+    ("dedup", "⍷"),
+    ("count", "⊒"),
+    ("join", "∾"),
+    ("reverse", "⇌"),
+    ("rotate", "↻"),
+    ("replicate", "▽"),
+    ("group", "⊕"),
+    ("partition", "⊜"),
+    ("windows", "◫"),
+    ("chunk", "⊟"),
+    ("zip", "⋈"),
+    ("unzip", "⋉"),
+    ("table", "⊞"),
+    ("shape", "△"),
+    ("reshape", "↯"),
+    ("rank", "⍤"),
+    ("transpose", "⍉"),
+    ("ravel", "♭"),
+    ("flatten", "♭"),
+    // ("exp", "exp"),
+    // ("abs", "abs"),
+    // ("floor", "floor"),
+    // ("ceil", "ceil"),
+    // ("round", "round"),
+    // ("trunc", "trunc"),
+    // ("sign", "sign"),
+    // ("gamma", "Γ"),
+    // ("digamma", "ψ"),
+    // ("beta", "β"),
+    // ("zeta", "ζ"),
+    // ("erf", "erf"),
+    // ("erfc", "erfc"),
+    // ("erfcinv", "erfcinv"),
+    // ("erfinv", "erfinv"),
+    // ("gamma_inc", "Γ"),
+    // ("gamma_inc_inv", "Γ⁻¹"),
+    // ("beta_inc", "β"),
+    // ("beta_inc_inv", "β⁻¹"),
+    // ("zeta", "ζ"),
+    // ("zeta_inv", "ζ⁻¹"),
+    // ("polygamma", "ψ"),
+    // ("polygamma_inv", "ψ⁻¹"),
+    // ("digamma", "ψ"),
+    // ("digamma_inv", "ψ⁻¹"),
+    ("factorial", "!"),
+];
+
+/// # Formatter
+///
+/// Turns ASCII spellings into glyphs, like `sqrt 4` into `√ 4`, see
+/// `SYMBOLS`.
+///
+/// The source is read like the lexer reads it, one character at a time, the
+/// longest name starting at each one being replaced. So `sinh` is `ⓔ` rather
+/// than `◯h`, and `epsilon` is `ε` with no `π` in it. Comments and string
+/// literals are kept as they are.
+///
+/// Names are ASCII and glyphs aren't, so formatting twice changes nothing.
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::formatter::Formatter;
+///
+/// let formatted = Formatter::new("sinh pi # pi".to_owned()).format();
+/// assert_eq!(formatted, "ⓔ π # pi");
+/// assert_eq!(Formatter::new(formatted.clone()).format(), formatted);
+/// ```
 pub struct Formatter {
     src: String,
-    symbols: HashMap<&'static str, &'static str>,
+    /// Longest first, so the first one matching is the longest
+    symbols: Vec<(String, String)>,
 }
 
 impl Formatter {
     pub fn new(src: String) -> Self {
-        let mut symbols = [
-            ("_", "‿"),
-            ("infinity", "∞"),
-            ("epsilon", "ε"),
-            ("pi", "π"),
-            ("tau", "τ"),
-            ("alpha", "α"),
-            ("iota", "ι"),
-            (":", "↕"),
-            ("flip", "↕"),
-            ("swap", "↔"),
-            ("`", "⁻"),
-            ("*", "×"),
-            ("%", "÷"),
-            ("pow", "ⁿ"),
-            ("log", "ₙ"),
-            ("croot", "∛"),
-            ("cbrt", "∛"),
-            ("sqrt", "√"),
-            ("root", "√"),
-            ("sin", "◯"),
-            ("sinh", "ⓔ"),
-            ("sins", "Ⓞ"),
-            ("inverse", "⁻¹"),
-            // ("exp", "exp"),
-            // ("abs", "abs"),
-            // ("floor", "floor"),
-            // ("ceil", "ceil"),
-            // ("round", "round"),
-            // ("trunc", "trunc"),
-            // ("sign", "sign"),
-            // ("gamma", "Γ"),
-            // ("digamma", "ψ"),
-            // ("beta", "β"),
-            // ("zeta", "ζ"),
-            // ("erf", "erf"),
-            // ("erfc", "erfc"),
-            // ("erfcinv", "erfcinv"),
-            // ("erfinv", "erfinv"),
-            // ("gamma_inc", "Γ"),
-            // ("gamma_inc_inv", "Γ⁻¹"),
-            // ("beta_inc", "β"),
-            // ("beta_inc_inv", "β⁻¹"),
-            // ("zeta", "ζ"),
-            // ("zeta_inv", "ζ⁻¹"),
-            // ("polygamma", "ψ"),
-            // ("polygamma_inv", "ψ⁻¹"),
-            // ("digamma", "ψ"),
-            // ("digamma_inv", "ψ⁻¹"),
-            ("factorial", "!"),
-        ];
-        symbols.sort_by(|fst, snd| match fst.0.len() {
-            x if x > snd.0.len() => std::cmp::Ordering::Less,
-            x if x < snd.0.len() => std::cmp::Ordering::Greater,
-            _ => std::cmp::Ordering::Equal,
-        });
-
-        Self {
+        let mut formatter = Self {
             src,
-            // Largest to smallest
-            symbols: HashMap::from(symbols),
+            symbols: SYMBOLS
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        };
+        formatter.sort();
+
+        formatter
+    }
+
+    /// Adds aliases on top of the default ones, like the names of builtins
+    /// registered with `Env::register`, replacing those with the same name.
+    ///
+    /// Aliases of ASCII glyphs are skipped, those can be typed as is and
+    /// could be read as part of another name once formatted.
+    pub fn with_aliases(self, aliases: Vec<(String, char)>) -> Self {
+        let aliases = aliases
+            .into_iter()
+            .filter(|(_, glyph)| !glyph.is_ascii())
+            .map(|(name, glyph)| (name, glyph.to_string()))
+            .collect::<Vec<_>>();
+
+        self.with_snippets(&aliases)
+    }
+
+    /// Adds names for glyphs or snippets of many tokens, like those of
+    /// `Config::aliases`, replacing those with the same name. Snippets are
+    /// formatted first, so they can use names too
+    pub fn with_snippets(mut self, snippets: &[(String, String)]) -> Self {
+        for (name, snippet) in snippets {
+            if !name.is_ascii() {
+                continue;
+            }
+            let snippet = rewrite(snippet, &self.symbols);
+            self.symbols.retain(|(other, _)| other != name);
+            self.symbols.push((name.clone(), snippet));
+        }
+        self.sort();
+
+        self
+    }
+
+    pub fn format(&self) -> String {
+        rewrite(&self.src, &self.symbols)
+    }
+
+    /// # Unformat
+    ///
+    /// The other way around, glyphs into ASCII names, for places without
+    /// good Unicode fonts. Names already there, and glyphs with no name, like
+    /// those of some user functions, are kept.
+    ///
+    /// A glyph with many names gets the shortest word, `↕` being `flip`
+    /// rather than `:`. Names are spaced out from the words and numbers next
+    /// to them, `◯ι3` being `sin iota 3` rather than `siniota3`, which also
+    /// keeps them from being read as another name. Formatting gives the same
+    /// program back, with those spaces.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use kuhi::formatter::Formatter;
+    ///
+    /// let unformatted = Formatter::new("⁻¹◯ⓔ ↕ 1‿2 ×2 # ◯".to_owned()).unformat();
+    /// assert_eq!(unformatted, "inverse sin sinh flip 1_2 *2 # ◯");
+    /// assert_eq!(Formatter::new(unformatted).format(), "⁻¹ ◯ ⓔ ↕ 1‿2 ×2 # ◯");
+    /// ```
+    pub fn unformat(&self) -> String {
+        let names = self.names();
+        let mut unformatted = String::with_capacity(self.src.len());
+        // Where the formatter reads anew, no name going past what is before
+        let mut start = 0;
+        let mut named = false;
+
+        // So names already there are read whole
+        let formatted = self.format();
+        for (text, name) in pieces(&formatted, &names) {
+            let piece = name.unwrap_or(text);
+            let word = |c: char| c.is_ascii_alphanumeric();
+            let readable =
+                (named || name.is_some()) && unformatted.ends_with(word) && piece.starts_with(word);
+            let read = &unformatted[start..];
+            let apart = rewrite(read, &self.symbols) + &rewrite(piece, &self.symbols);
+            if readable || rewrite(&format!("{read}{piece}"), &self.symbols) != apart {
+                unformatted.push(' ');
+                start = unformatted.len();
+            }
+            unformatted.push_str(piece);
+            named = name.is_some();
+
+            // Comments and strings are read whole
+            if text.starts_with(['#', '"']) {
+                start = unformatted.len();
+            } else if let Some((i, c)) = unformatted[start..]
+                .char_indices()
+                .rfind(|(_, c)| !self.symbols.iter().any(|(name, _)| name.contains(*c)))
+            {
+                start += i + c.len_utf8();
+            }
         }
+
+        unformatted
+    }
+
+    /// The names `src` is read as having, in order
+    pub(crate) fn read<'a>(&'a self, src: &'a str) -> Vec<&'a str> {
+        pieces(src, &self.symbols)
+            .into_iter()
+            .filter_map(|(text, replacement)| replacement.map(|_| text))
+            .collect()
     }
 
-    pub fn format(&mut self) -> String {
-        let mut src = self.src.clone();
-        let symbols = self.symbols.clone();
+    /// The name each glyph is unformatted to, longest glyphs first. ASCII
+    /// glyphs, like `!`, and snippets with some ASCII in them are kept
+    fn names(&self) -> Vec<(String, String)> {
+        // Words first, then the shortest
+        let rank = |name: &str| (!name.chars().all(|c| c.is_ascii_alphabetic()), name.len());
+        let mut names: Vec<(String, String)> = vec![];
 
-        for (from, to) in symbols.into_iter() {
-            src = src.replace(from, to);
+        for (name, glyph) in &self.symbols {
+            if glyph.contains(|c: char| c.is_ascii()) {
+                continue;
+            }
+            match names.iter_mut().find(|(seen, _)| seen == glyph) {
+                Some((_, other)) if rank(name) < rank(other) => *other = name.clone(),
+                Some(_) => {}
+                None => names.push((glyph.clone(), name.clone())),
+            }
         }
+        names.sort_by_key(|(glyph, _)| std::cmp::Reverse(glyph.len()));
 
-        src.clone()
+        names
     }
+
+    fn sort(&mut self) {
+        // Stable, so names of the same length keep their order
+        self.symbols
+            .sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    }
+}
+
+/// Splits `src` like the lexer reads it, into comments, string literals, the
+/// longest of the keys of `table` along with what they are replaced by, or
+/// else single characters
+fn pieces<'a>(src: &'a str, table: &'a [(String, String)]) -> Vec<(&'a str, Option<&'a str>)> {
+    let mut pieces = vec![];
+    let mut rest = src;
+
+    while let Some(c) = rest.chars().next() {
+        let (len, replacement) = match c {
+            '#' => (comment(rest), None),
+            '"' => (string(rest), None),
+            _ => match table
+                .iter()
+                .find(|(from, _)| rest.starts_with(from.as_str()))
+            {
+                Some((from, to)) => (from.len(), Some(to.as_str())),
+                None => (c.len_utf8(), None),
+            },
+        };
+        pieces.push((&rest[..len], replacement));
+        rest = &rest[len..];
+    }
+
+    pieces
+}
+
+/// Replaces the keys of `table` in `src`, see `pieces`
+fn rewrite(src: &str, table: &[(String, String)]) -> String {
+    pieces(src, table)
+        .into_iter()
+        .map(|(text, replacement)| replacement.unwrap_or(text))
+        .collect()
+}
+
+/// How long the comment at the start of `src` is, up to the end of `src`
+/// when it isn't closed, which is left for the parser to report
+fn comment(src: &str) -> usize {
+    let mut chars = src[1..].chars().peekable();
+    // Only the text is needed
+    let mut loc = Loc {
+        start: 0,
+        end: 0,
+        line: 1,
+        column: 1,
+    };
+
+    match skip_comment(&mut chars, &mut loc) {
+        Ok(comment) => 1 + comment.len(),
+        Err(_) => src.len(),
+    }
+}
+
+/// How long the string literal at the start of `src` is, up to the end of
+/// `src` when it isn't closed. `\"` doesn't close it
+fn string(src: &str) -> usize {
+    let mut chars = src.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+
+    src.len()
 }
@@ -0,0 +1,198 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use crate::{
+    builtins::CustomBuiltin,
+    check::check,
+    err::{Diagnostic, ErrorKind, RuntimeError},
+    limits::Limits,
+    parser::{parse, Loc},
+    value::Value,
+    vm::Env,
+};
+
+/// # Interpreter
+///
+/// Evaluates kuhi source one piece at a time, keeping the stack and the
+/// functions defined between calls, like the REPL does.
+///
+/// A failed `eval` leaves the stack and the definitions as they were before
+/// it. Sources are taken as is, ASCII spellings like `sqrt` need to go
+/// through `Formatter` first.
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::Interpreter;
+///
+/// let mut kuhi = Interpreter::new();
+/// kuhi.eval("ⁿ10 2").unwrap();
+/// assert_eq!(kuhi.pop_as::<i64>().unwrap(), 1024);
+///
+/// kuhi.push(0.5);
+/// kuhi.eval("×4").unwrap();
+/// assert_eq!(kuhi.pop_as::<f64>().unwrap(), 2.0);
+///
+/// // Nothing to add 1 to
+/// assert!(kuhi.eval("+1").is_err());
+/// ```
+pub struct Interpreter {
+    env: Env,
+    loc: Loc,
+    source: String,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            env: Env::new(vec![]),
+            loc: Loc {
+                start: 0,
+                end: 0,
+                line: 1,
+                column: 1,
+            },
+            source: String::new(),
+        }
+    }
+
+    /// Runs `src` on the stack, giving back the whole stack, its top last.
+    ///
+    /// Fails with every syntax error in `src`, or else with the first error
+    /// found when checking or running it.
+    pub fn eval(&mut self, src: &str) -> Result<Vec<Value>, Vec<Diagnostic>> {
+        let mut src = src.to_owned();
+        if !src.ends_with('\n') {
+            src.push('\n');
+        }
+        self.source.push_str(&src);
+
+        // Even when it doesn't parse, `loc` is left where the next source starts
+        let tokens = match parse(&src, &mut self.loc) {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                return Err(errors
+                    .into_iter()
+                    .map(|(err, loc)| Diagnostic::new(ErrorKind::Syntax(err), loc, &self.source))
+                    .collect());
+            }
+        };
+
+        if let Err((err, loc)) = check(&tokens, &self.env) {
+            return Err(vec![Diagnostic::new(
+                ErrorKind::Check(err),
+                loc,
+                &self.source,
+            )]);
+        }
+
+        let stack = self.env.stack.clone();
+        let functions = self.env.functions.clone();
+        // Compiled from the functions, so going back with them
+        let compiled = self.env.compiled.clone();
+        if let Err((err, loc, context)) = self.env.repurpose(&tokens).run() {
+            self.env.stack = stack;
+            self.env.functions = functions;
+            self.env.compiled = compiled;
+            return Err(vec![Diagnostic::new(
+                ErrorKind::Runtime(err),
+                loc,
+                &self.source,
+            )
+            .with_context(*context, &self.source)]);
+        }
+
+        Ok(self.env.stack.clone())
+    }
+
+    pub fn push(&mut self, value: impl Into<Value>) -> &mut Self {
+        self.env.stack.push(value.into());
+
+        self
+    }
+
+    pub fn pop(&mut self) -> Option<Value> {
+        self.env.stack.pop()
+    }
+
+    /// Pops the top of the stack as a Rust value, like `i64` or `Rational`,
+    /// leaving it there when it can't be converted
+    pub fn pop_as<T: TryFrom<Value, Error = RuntimeError>>(&mut self) -> Result<T, RuntimeError> {
+        let top = self
+            .env
+            .stack
+            .last()
+            .cloned()
+            .ok_or(RuntimeError::InvalidPop { len: 0, arity: 1 })?;
+
+        let value = T::try_from(top)?;
+        self.env.stack.pop();
+        Ok(value)
+    }
+
+    /// The stack, its top last
+    pub fn stack(&self) -> &[Value] {
+        &self.env.stack
+    }
+
+    /// See `Env::register`
+    pub fn register(&mut self, glyph: char, builtin: CustomBuiltin) -> &mut Self {
+        self.env.register(glyph, builtin);
+
+        self
+    }
+
+    /// See `Limits`, only `Limits::depth` is set by default
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.env.limits = limits;
+
+        self
+    }
+
+    /// Looks for lossy numeric steps while running, see `Warning`, which
+    /// `Interpreter::warnings` then gives back
+    pub fn set_warnings(&mut self, enabled: bool) -> &mut Self {
+        self.env.warnings = enabled.then(Vec::new);
+
+        self
+    }
+
+    /// Takes the warnings found since they were last taken, failed `eval`s
+    /// included, in the order they were found
+    pub fn warnings(&mut self) -> Vec<Diagnostic> {
+        let Some(warnings) = &mut self.env.warnings else {
+            return vec![];
+        };
+
+        warnings
+            .drain(..)
+            .map(|(warning, loc)| Diagnostic::new(ErrorKind::Warning(warning), loc, &self.source))
+            .collect()
+    }
+
+    /// A flag stopping the running `eval` once set, which can be done from
+    /// another thread, like a Ctrl-C handler. It is left set, so it needs
+    /// to be cleared before the next `eval`
+    pub fn canceller(&self) -> Arc<AtomicBool> {
+        self.env.cancel.clone()
+    }
+
+    /// The line the next source starts on
+    pub fn line(&self) -> usize {
+        self.loc.line
+    }
+
+    /// Everything evaluated so far, which the locations of errors point into
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
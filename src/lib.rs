@@ -1,8 +1,20 @@
 #![feature(iterator_try_reduce)]
 
+pub mod array;
 pub mod builtins;
+pub mod bytecode;
+pub mod check;
+pub mod config;
 pub mod err;
+pub mod explain;
+pub mod interpreter;
+pub mod limits;
 pub mod parser;
+pub mod suggest;
 pub mod value;
 pub mod vm;
+mod warnings;
 pub mod formatter;
+
+pub use err::Diagnostic;
+pub use interpreter::Interpreter;
@@ -0,0 +1,126 @@
+use std::cell::Cell;
+
+use rug::Integer;
+
+use crate::{err::RuntimeError, value::Value};
+
+/// # Limits
+///
+/// How much a program may do before it is stopped, `None` being no limit.
+/// Only `depth` is set by default, see `DEPTH`.
+///
+/// `steps` counts instructions, those of function bodies included, over a
+/// single `Env::run` \
+/// `elements` is the most values a single value may hold, counting those of
+/// nested lists \
+/// `bits` is the largest an integer may get, the numerator and denominator
+/// of a rational counting separately \
+/// `depth` is how deep calls may nest, those of functions, scopes and tuples,
+/// each one taking some of the thread's native stack
+///
+/// Builtins that can build large values, like `ι` or `ⁿ`, check them before
+/// building them, the others are checked once they are done.
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::{limits::Limits, Interpreter};
+///
+/// let mut kuhi = Interpreter::new();
+/// kuhi.set_limits(Limits {
+///     elements: Some(1000),
+///     ..Limits::default()
+/// });
+///
+/// assert!(kuhi.eval("ι100").is_ok());
+/// assert!(kuhi.eval("ι1000000000").is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub steps: Option<u64>,
+    pub elements: Option<usize>,
+    pub bits: Option<u64>,
+    pub depth: Option<usize>,
+}
+
+/// The default `Limits::depth`, which fits in the 2 MiB of stack threads
+/// are spawned with, even in debug builds
+pub const DEPTH: usize = 200;
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            steps: None,
+            elements: None,
+            bits: None,
+            depth: Some(DEPTH),
+        }
+    }
+}
+
+thread_local! {
+    /// The limits of the program being ran, for builtins to check
+    static ACTIVE: Cell<Limits> = Cell::new(Limits::default());
+}
+
+/// Makes `limits` the ones builtins check, until the guard is dropped
+pub(crate) fn enter(limits: Limits) -> Guard {
+    Guard(ACTIVE.replace(limits))
+}
+
+pub(crate) struct Guard(Limits);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        ACTIVE.set(self.0);
+    }
+}
+
+/// Fails when a value of `n` elements would be too big
+pub(crate) fn elements(n: usize) -> Result<(), RuntimeError> {
+    match ACTIVE.get().elements {
+        Some(max) if n > max => Err(RuntimeError::ElementLimit(max)),
+        _ => Ok(()),
+    }
+}
+
+/// Fails when an integer of `n` bits would be too big
+pub(crate) fn bits(n: u64) -> Result<(), RuntimeError> {
+    match ACTIVE.get().bits {
+        Some(max) if n > max => Err(RuntimeError::BitLimit(max)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks a value that is already built against both size limits
+pub(crate) fn check(value: &Value) -> Result<(), RuntimeError> {
+    let limits = ACTIVE.get();
+    if limits.elements.is_none() && limits.bits.is_none() {
+        return Ok(());
+    }
+
+    let mut count = 0;
+    __walk(value, &mut count)
+}
+
+fn __walk(value: &Value, count: &mut usize) -> Result<(), RuntimeError> {
+    match value {
+        Value::Integer(n) => bits(__bits(n)),
+        Value::Rational(r) => bits(__bits(r.numer()).max(__bits(r.denom()))),
+        Value::List(vals) | Value::Tuple(vals) => {
+            *count += vals.len();
+            elements(*count)?;
+            vals.iter().try_for_each(|val| __walk(val, count))
+        }
+        Value::Array(array) => {
+            *count += array.data.len();
+            elements(*count)?;
+            array.data.iter().try_for_each(|val| __walk(val, count))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn __bits(n: &Integer) -> u64 {
+    u64::from(n.significant_bits())
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::Ordering;
+
 use codespan_reporting::{
     diagnostic::{Diagnostic, Label},
     files::SimpleFile,
@@ -7,91 +9,207 @@ use codespan_reporting::{
     },
 };
 use kuhi::{
+    config::Config,
+    err::{ErrorKind, RuntimeError, StackValue},
+    explain::explain,
     formatter::Formatter,
-    parser::{parse, Loc},
-    vm::Env,
+    limits::Limits,
+    Interpreter,
 };
 use rustyline::DefaultEditor;
 
+/// How errors are written to stderr, set with `--error-format=…`
+#[derive(PartialEq)]
+enum ErrorFormat {
+    /// Rendered with the source, for people
+    Human,
+    /// One object per line, see `Diagnostic::to_json`
+    Json,
+}
+
 fn main() -> anyhow::Result<()> {
+    let mut error_format = ErrorFormat::Human;
+    let mut warnings = true;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--error-format=human" => error_format = ErrorFormat::Human,
+            "--error-format=json" => error_format = ErrorFormat::Json,
+            "--no-warnings" => warnings = false,
+            // Prints a file, or stdin, with ASCII names rather than glyphs
+            "unformat" => {
+                let src = match args.next() {
+                    Some(path) => std::fs::read_to_string(path)?,
+                    None => std::io::read_to_string(std::io::stdin())?,
+                };
+                let formatter = Formatter::new(src).with_snippets(&aliases(&error_format)?);
+                print!("{}", formatter.unformat());
+                return Ok(());
+            }
+            "--explain" => {
+                let code = args.next().unwrap_or_default();
+                match explain(&code) {
+                    Some(explanation) => println!("{explanation}"),
+                    None => anyhow::bail!("no error has the code `{code}`, they look like `K0001`"),
+                }
+                return Ok(());
+            }
+            _ => anyhow::bail!(
+                "unknown argument `{arg}`, expected `--error-format=human`, `--error-format=json`, `--no-warnings`, `--explain <code>` or `unformat [file]`"
+            ),
+        }
+    }
+
     let writer = StandardStream::stderr(ColorChoice::Always);
     let config = codespan_reporting::term::Config::default();
     let mut editor = DefaultEditor::new()?;
     let _ = editor.load_history("history.txt");
 
-    let mut env = Env::new(vec![]);
-    let mut loc = Loc {
-        start: 0,
-        end: 0,
-        line: 1,
-        column: 1,
-    };
-
-    let mut full_input = String::new();
+    let aliases = aliases(&error_format)?;
+    let mut kuhi = Interpreter::new();
+    kuhi.set_limits(Limits {
+        steps: None,
+        elements: Some(10_000_000),
+        bits: Some(1 << 24),
+        // Well within the main thread's 8 MiB of stack
+        depth: Some(1000),
+    })
+    .set_warnings(warnings);
+
+    // Ctrl-C stops what is running, rustyline handles it while reading
+    let cancel = kuhi.canceller();
+    ctrlc::set_handler({
+        let cancel = cancel.clone();
+        move || cancel.store(true, Ordering::Relaxed)
+    })?;
 
     // REPL
     loop {
-        let mut input = editor.readline(&format!("{}> ", loc.line))?;
+        let mut input = editor.readline(&format!("{}> ", kuhi.line()))?;
 
         if input == ":q" {
             editor.save_history("history.txt")?;
             break;
         }
 
-        input = Formatter::new(input).format();
+        input = Formatter::new(input)
+            .with_aliases(kuhi.env().aliases())
+            .with_snippets(&aliases)
+            .format();
 
         editor.add_history_entry(input.clone())?;
 
-        input.push('\n');
-        full_input.push_str(&input.clone());
-
-        let file = SimpleFile::new("<repl>", full_input.clone());
-
-        let tokens = match parse(&input, &mut loc) {
-            Ok(tokens) => {
-                // dbg!(tokens)
-                tokens
+        cancel.store(false, Ordering::Relaxed);
+        let result = kuhi.eval(&input);
+
+        // Found while running, so before the errors
+        let warnings = kuhi.warnings();
+        let errors = result.as_ref().err().map_or(&[][..], Vec::as_slice);
+        let file = SimpleFile::new("<repl>", kuhi.source());
+        for diagnostic in warnings.iter().chain(errors) {
+            match error_format {
+                ErrorFormat::Human => {
+                    term::emit(&mut writer.lock(), &config, &file, &report(diagnostic))?
+                }
+                ErrorFormat::Json => eprintln!("{}", diagnostic.to_json()),
             }
-            Err(err) => {
-                dbg!(loc.clone());
-                dbg!(full_input);
-                let start = err.1.start;
-                let end = err.1.end + 1;
-
-                let diagnostic = Diagnostic::error()
-                    .with_message("Syntax error")
-                    .with_labels(vec![
-                        Label::primary((), start..end).with_message(err.0.to_string())
-                    ])
-                    .with_notes(vec![err.0.note()]);
-
-                term::emit(&mut writer.lock(), &config, &file, &diagnostic)?;
-
-                break;
+        }
+
+        match result {
+            Ok(_) => println!("{}", kuhi.env()),
+            Err(errors) => {
+                let cancelled = errors
+                    .iter()
+                    .all(|err| matches!(err.kind, ErrorKind::Runtime(RuntimeError::Cancelled)));
+                if !cancelled {
+                    break;
+                }
             }
-        };
+        }
+    }
 
-        match env.repurpose(&tokens).run() {
-            Ok(_) => println!("{env}"),
-            Err(err) => {
-                let start = err.1.start;
-                let end = err.1.end + 1;
+    editor.save_history("history.txt")?;
 
-                let diagnostic = Diagnostic::error()
-                    .with_message("Runtime error")
-                    .with_labels(vec![
-                        Label::primary((), start..end).with_message(err.0.to_string())
-                    ])
-                    .with_notes(vec![err.0.note()]);
+    Ok(())
+}
 
-                term::emit(&mut writer.lock(), &config, &file, &diagnostic)?;
+/// The aliases of the user's config, reporting those left out, see `Config`.
+/// A config that can't be read is reported too, and has none
+fn aliases(error_format: &ErrorFormat) -> anyhow::Result<Vec<(String, String)>> {
+    let (config, problems) = match Config::load() {
+        Ok(config) => {
+            let problems = config.conflicts.iter().map(ToString::to_string).collect();
+            (config, problems)
+        }
+        Err(err) => (Config::default(), vec![err.to_string()]),
+    };
 
-                break;
-            }
+    let path = Config::path().unwrap_or_default();
+    let writer = StandardStream::stderr(ColorChoice::Always);
+    for problem in problems {
+        match error_format {
+            ErrorFormat::Human => term::emit(
+                &mut writer.lock(),
+                &term::Config::default(),
+                &SimpleFile::new("", ""),
+                &Diagnostic::warning()
+                    .with_message(problem)
+                    .with_notes(vec![format!("in `{}`", path.display())]),
+            )?,
+            ErrorFormat::Json => eprintln!(
+                "{}",
+                serde_json::json!({ "kind": "config", "message": problem, "path": path })
+            ),
         }
     }
 
-    editor.save_history("history.txt")?;
+    Ok(config.aliases)
+}
 
-    Ok(())
+/// Renders an error with the calls it happened in and the stack before it
+fn report(err: &kuhi::Diagnostic) -> Diagnostic<()> {
+    let diagnostic = match err.kind {
+        ErrorKind::Warning(_) => Diagnostic::warning(),
+        _ => Diagnostic::error(),
+    };
+
+    diagnostic
+        .with_message(err.title())
+        .with_code(err.code())
+        .with_labels(
+            std::iter::once(Label::primary((), err.span.clone()).with_message(err.message()))
+                .chain(err.trace.iter().map(|(frame, span)| {
+                    Label::secondary((), span.clone()).with_message(frame.to_string())
+                }))
+                .collect(),
+        )
+        .with_notes(
+            std::iter::once(err.note())
+                .chain(err.help.clone())
+                .chain(stack_note(&err.stack))
+                .chain([format!("run `kuhi --explain {}` for more", err.code())])
+                .collect(),
+        )
+}
+
+/// The stack right before the step that failed, numbered like the REPL does
+fn stack_note(stack: &[StackValue]) -> Option<String> {
+    if stack.is_empty() {
+        return None;
+    }
+
+    let values = stack
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, value)| {
+            let types = value.types().into_iter().rev().collect::<Vec<_>>();
+            format!("[{}] {} : {}", i + 1, value, types.join(" ⊂ "))
+        })
+        .collect::<Vec<_>>();
+
+    Some(format!(
+        "the stack before this step:\n{}",
+        values.join("\n")
+    ))
 }
@@ -1,8 +1,14 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    iter::Peekable,
+    str::Chars,
+};
 
 use rug::{Complex, Integer, Rational};
 
-use crate::err::SyntaxError;
+use crate::{builtins::BUILTINS, err::SyntaxError};
+
+pub type SyntaxErrors = Vec<(SyntaxError, Loc)>;
 
 /// Location of a token in the source code
 ///
@@ -34,6 +40,7 @@ pub enum Token {
     _UnfinishedList(Vec<(Token, Loc)>),
 
     Scope(Vec<(Token, Loc)>),
+    Tuple(Vec<(Token, Loc)>),
 
     Dup,
     Pop,
@@ -43,6 +50,14 @@ pub enum Token {
     Function(Vec<(Token, Loc)>),
     FunctionCall(char),
     Inverse(Box<Token>, Loc),
+    /// A binary builtin undone knowing its right operand, only made when
+    /// inverting a function, see `Inverse`
+    RightInverse(char),
+    /// `⍜f g`, runs `g` under `f`
+    Under(Box<(Token, Loc)>, Box<(Token, Loc)>),
+    /// Name, whether it defines the inverse, body
+    Definition(char, bool, Vec<(Token, Loc)>),
+    _Arrow,
 
     Spacing, // Otherwise Complex parsing consumes the previous token even when seperated by a space
     InvalidState,
@@ -65,6 +80,7 @@ impl Clone for Token {
             _UnfinishedList(tokens) => _UnfinishedList(tokens.clone()),
 
             Scope(tokens) => Scope(tokens.clone()),
+            Tuple(tokens) => Tuple(tokens.clone()),
 
             Dup => Dup,
             Pop => Pop,
@@ -74,6 +90,10 @@ impl Clone for Token {
             Function(tokens) => Function(tokens.clone()),
             FunctionCall(f) => FunctionCall(f.clone()),
             Inverse(tok, loc) => Inverse(Box::new(*tok.clone()), loc.clone()),
+            RightInverse(c) => RightInverse(*c),
+            Under(f, g) => Under(f.clone(), g.clone()),
+            Definition(name, inverse, body) => Definition(*name, *inverse, body.clone()),
+            _Arrow => _Arrow,
 
             Spacing => Spacing,
             InvalidState => InvalidState,
@@ -135,6 +155,17 @@ impl Display for Token {
                         .join(" ")
                 )
             }
+            Tuple(tokens) => {
+                write!(
+                    f,
+                    "⟨{}⟩",
+                    tokens
+                        .iter()
+                        .map(|v| v.0.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )
+            }
 
             Dup => write!(f, "."),
             Pop => write!(f, ","),
@@ -150,6 +181,15 @@ impl Display for Token {
             }
             FunctionCall(c) => write!(f, "{}", c),
             Inverse(tok, _) => write!(f, "⁻¹{}", tok.to_string()),
+            RightInverse(c) => write!(f, "<right ⁻¹{}>", c),
+            Under(g, h) => write!(f, "⍜{}{}", g.0, h.0),
+            Definition(name, inverse, body) => {
+                if *inverse {
+                    write!(f, "⁻¹")?;
+                }
+                write!(f, "{} ← {}", name, Function(body.clone()))
+            }
+            _Arrow => write!(f, "←"),
 
             Spacing => write!(f, ""),
             InvalidState => write!(f, "<InvalidState>"),
@@ -157,9 +197,142 @@ impl Display for Token {
     }
 }
 
-pub fn parse(
+/// Parses `input`, or gives back every syntax error in it, sorted by where
+/// they are.
+///
+/// Errors inside brackets are recovered from at the closing bracket, as if
+/// the brackets were empty, and closing brackets opening nothing are skipped.
+/// Otherwise each statement is parsed on its own, see `statements`. `loc` is
+/// left at the end of `input` either way.
+pub fn parse(input: &str, loc: &mut Loc) -> Result<Vec<(Token, Loc)>, SyntaxErrors> {
+    let start = loc.clone();
+    let mut first = vec![];
+    match parse_statement(input, loc, &mut first) {
+        Ok(tokens) if first.is_empty() => return Ok(tokens),
+        Ok(_) => {}
+        Err((err, at, _)) => first.push((err, at)),
+    }
+
+    // Only split into statements when there are errors, a valid program may
+    // have an inverse or a definition running over a line
+    let mut errors = vec![];
+    *loc = start.clone();
+    for statement in statements(input) {
+        let start = loc.clone();
+        if let Err((err, at, _)) = parse_statement(statement, loc, &mut errors) {
+            errors.push((err, at));
+        }
+        *loc = advance(&start, statement);
+    }
+    if errors.is_empty() {
+        errors = first;
+    }
+    errors.sort_by_key(|(_, loc)| loc.start);
+
+    *loc = advance(&start, input);
+    Err(errors)
+}
+
+/// Parses the body of a bracket, or gives back nothing when it has errors,
+/// which go to `errors`. Unlike `parse`, it isn't parsed again statement by
+/// statement, the whole of `input` being parsed again that way already.
+fn parse_body(input: &str, loc: &mut Loc, errors: &mut SyntaxErrors) -> Vec<(Token, Loc)> {
+    let (start, found) = (loc.clone(), errors.len());
+    match parse_statement(input, loc, errors) {
+        Ok(tokens) if errors.len() == found => return tokens,
+        Ok(_) => {}
+        Err((err, at, _)) => errors.push((err, at)),
+    }
+
+    *loc = advance(&start, input);
+    vec![]
+}
+
+/// Splits `input` into statements, each running to the end of its line, or
+/// further while a bracket is left open.
+///
+/// A bracket that is never closed ends its statement at the end of its line,
+/// so the lines after it are still parsed on their own.
+fn statements(input: &str) -> Vec<&str> {
+    let mut statements = vec![];
+    let mut start = 0;
+    // Where the brackets left open are
+    let mut open = vec![];
+    let mut chars = input.chars().peekable();
+    // Where the next char starts
+    let mut end = 0;
+
+    while let Some(c) = chars.next() {
+        let i = end;
+        end += c.len_utf8();
+
+        match c {
+            '(' | '[' | '⟨' => open.push((i, c)),
+            // A closer that doesn't match is stray, it closes nothing
+            ')' | ']' | '⟩' => {
+                let opener = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '⟨',
+                };
+                if open.last().is_some_and(|&(_, last)| last == opener) {
+                    open.pop();
+                }
+            }
+            '#' if chars.peek() == Some(&'{') => {
+                block_comment(&mut chars, |c| end += c.len_utf8());
+            }
+            '#' => {
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    end += c.len_utf8();
+                }
+            }
+            '\n' if open.is_empty() => {
+                statements.push(&input[start..=i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(&(first, _)) = open.first() {
+        let end = input[first..]
+            .find('\n')
+            .map_or(input.len(), |n| first + n + 1);
+        statements.push(&input[start..end]);
+        statements.extend(self::statements(&input[end..]));
+    } else if start < input.len() {
+        statements.push(&input[start..]);
+    }
+
+    statements
+}
+
+/// Where parsing `text` from `loc` ends
+fn advance(loc: &Loc, text: &str) -> Loc {
+    let end = loc.end + text.len();
+    let (line, column) = match text.rfind('\n') {
+        Some(i) => (
+            loc.line + text.matches('\n').count(),
+            text[i + 1..].chars().count() + 1,
+        ),
+        None => (loc.line, loc.column + text.chars().count()),
+    };
+
+    Loc {
+        start: end,
+        end,
+        line,
+        column,
+    }
+}
+
+/// Parses `input` up to its first error, errors inside brackets going to
+/// `errors` instead, see `parse`
+fn parse_statement(
     input: &str,
     loc: &mut Loc,
+    errors: &mut SyntaxErrors,
 ) -> Result<Vec<(Token, Loc)>, (SyntaxError, Loc, Vec<(Token, Loc)>)> {
     let mut tokens: Vec<(Token, Loc)> = Vec::new();
     let mut chars = input.chars().peekable();
@@ -173,10 +346,18 @@ pub fn parse(
             ' ' | '\r' => Token::Spacing,
             '\n' => {
                 loc.line += 1;
-                loc.column = 1;
+                // The column is bumped once more after the token is pushed
+                loc.column = 0;
 
                 Token::Spacing
             }
+            '#' => {
+                let start = loc.clone();
+                match skip_comment(&mut chars, loc) {
+                    Ok(_) => Token::Spacing,
+                    Err(err) => Err((err, start, tokens.clone()))?,
+                }
+            }
 
             '0'..='9' => (|| {
                 // Consume previous token if it's a ⁻
@@ -444,21 +625,20 @@ pub fn parse(
                 };
                 let value_loc = prev.1.clone();
 
-                // Check second to last token for an unfinished list
+                // Check the token before the value for an unfinished list
                 let prev = tokens.clone();
                 let otherwise = (Token::InvalidState.clone(), loc.clone());
-                let prev = &prev
-                    .get(prev.len().overflowing_sub(2).0)
-                    .unwrap_or(&otherwise);
+                let prev = &prev.last().unwrap_or(&otherwise);
 
                 match &prev.0 {
-                    Token::_UnfinishedList(tokens) => {
-                        let mut tokens = tokens.clone();
+                    Token::_UnfinishedList(list) => {
+                        let mut list = list.clone();
                         // set start of loc to previous token's
                         loc.start = prev.1.start;
                         loc.column = prev.1.column;
                         tokens.pop();
-                        Token::_UnfinishedList(tokens)
+                        list.push((value, value_loc));
+                        Token::_UnfinishedList(list)
                     }
                     Token::Spacing | Token::InvalidState | _ => {
                         Token::_UnfinishedList(vec![(value, value_loc)])
@@ -466,6 +646,13 @@ pub fn parse(
                 }
             }
 
+            '←' => Token::_Arrow,
+            // Temporary token value, post-processing will give it its functions
+            '⍜' => Token::Under(
+                Box::new((Token::InvalidState, loc.clone())),
+                Box::new((Token::InvalidState, loc.clone())),
+            ),
+
             '.' => Token::Dup,
             ',' => Token::Pop,
             '↔' => Token::Flip,
@@ -498,6 +685,13 @@ pub fn parse(
                             loc.column = 1;
                             continue;
                         }
+                        '#' => {
+                            let start = loc.clone();
+                            if let Err(err) = skip_comment(&mut chars, loc) {
+                                Err((err, start, tokens.clone()))?;
+                            }
+                            continue;
+                        }
                         _ => {}
                     }
                     if depth == 0 {
@@ -515,25 +709,28 @@ pub fn parse(
                 }
                 Token::_UnfinishedList(sub)
             }
-            ']' => Err((
-                SyntaxError::UnmatchedSquareBracket(true),
-                loc.clone(),
-                tokens.clone(),
-            ))?,
+            // Nothing was opened, so the rest can still be parsed
+            ']' => {
+                errors.push((SyntaxError::UnmatchedSquareBracket(true), loc.clone()));
+                Token::Spacing
+            }
 
             '(' => {
+                let open = loc.clone();
                 let mut depth = 1;
                 let mut sub = String::new();
+                // Same as for tuples, the body is parsed once the matching
+                // parenthesis is found, so the locations of its steps are exact
                 while let Some(c) = chars.next() {
-                    loc.end += 1;
-                    loc.column += 1;
-
                     match c {
                         '(' => depth += 1,
                         ')' => depth -= 1,
-                        '\n' => {
-                            loc.line += 1;
-                            loc.column = 1;
+                        '#' => {
+                            sub.push(c);
+                            match skip_comment(&mut chars, &mut loc.clone()) {
+                                Ok(comment) => sub.push_str(&comment),
+                                Err(err) => Err((err, open.clone(), tokens.clone()))?,
+                            }
                             continue;
                         }
                         _ => {}
@@ -544,20 +741,74 @@ pub fn parse(
                     sub.push(c);
                 }
                 if depth != 0 {
-                    loc.end = loc.start;
                     Err((
                         SyntaxError::UnmatchedParenthesis(false),
-                        loc.clone(),
+                        open.clone(),
                         tokens.clone(),
                     ))?;
                 }
-                Token::Function(parse(sub.as_str(), loc)?)
+
+                loc.end += c.len_utf8();
+                loc.column += 1;
+                loc.start = loc.end;
+                let body = parse_body(sub.as_str(), loc, errors);
+                loc.start = open.start;
+
+                Token::Function(body)
+            }
+            // Nothing was opened, so the rest can still be parsed
+            ')' => {
+                errors.push((SyntaxError::UnmatchedParenthesis(true), loc.clone()));
+                Token::Spacing
+            }
+
+            '⟨' => {
+                let open = loc.clone();
+                let mut depth = 1;
+                let mut sub = String::new();
+                // Only look for the matching bracket here, the contents are
+                // parsed afterwards so their locations stay exact
+                while let Some(c) = chars.next() {
+                    match c {
+                        '⟨' => depth += 1,
+                        '⟩' => depth -= 1,
+                        '#' => {
+                            sub.push(c);
+                            match skip_comment(&mut chars, &mut loc.clone()) {
+                                Ok(comment) => sub.push_str(&comment),
+                                Err(err) => Err((err, open.clone(), tokens.clone()))?,
+                            }
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    sub.push(c);
+                }
+                if depth != 0 {
+                    Err((
+                        SyntaxError::UnmatchedAngleBracket(false),
+                        open.clone(),
+                        tokens.clone(),
+                    ))?;
+                }
+
+                loc.end += c.len_utf8();
+                loc.column += 1;
+                loc.start = loc.end;
+                let inner = parse_body(sub.as_str(), loc, errors);
+                // `⟩` is as long as `⟨`, so it is accounted for below
+                loc.start = open.start;
+
+                Token::Tuple(inner)
+            }
+            // Nothing was opened, so the rest can still be parsed
+            '⟩' => {
+                errors.push((SyntaxError::UnmatchedAngleBracket(true), loc.clone()));
+                Token::Spacing
             }
-            ')' => Err((
-                SyntaxError::UnmatchedParenthesis(true),
-                loc.clone(),
-                tokens.clone(),
-            ))?,
 
             c => Token::FunctionCall(c),
         };
@@ -590,6 +841,23 @@ pub fn parse(
                     tokens.remove(j);
                     continue;
                 }
+                // Only numbers can be stranded, like before the `‿`
+                if !matches!(
+                    tokens[j].0,
+                    Token::Integer(_)
+                        | Token::Rational(_)
+                        | Token::Complex(_)
+                        | Token::Pi(_)
+                        | Token::E(..)
+                        | Token::Infinity
+                        | Token::Epsilon
+                ) {
+                    Err((
+                        SyntaxError::InvalidSymbol('‿'),
+                        tokens[j].1.clone(),
+                        tokens.clone(),
+                    ))?;
+                }
                 unfinished.push(tokens[j].clone());
                 tokens.remove(j);
                 break;
@@ -610,29 +878,178 @@ pub fn parse(
         })
         .collect();
 
-    // Have inverse consume the next token
-    let mut i = 0;
-    while i < tokens.len() {
-        if let Token::Inverse(_, loc) = tokens[i].0.clone() {
-            let mut loc = loc.clone();
-            // Size of the ⁻
-            loc.end += 2;
+    // Have inverse consume the next token, and under the next two. Starting
+    // from the end, so the tokens they consume are already complete
+    let mut i = tokens.len();
+    while i > 0 {
+        i -= 1;
+        match &tokens[i].0 {
+            Token::Inverse(tok, loc) if matches!(tok.as_ref(), Token::InvalidState) => {
+                let mut loc = loc.clone();
+                // Size of the ⁻
+                loc.end += 2;
+
+                let j = i + 1;
+                if j >= tokens.len() {
+                    Err((SyntaxError::LonelyInverse, loc.clone(), tokens.clone()))?;
+                }
 
-            let j = i + 1;
-            if j >= tokens.len() {
-                Err((SyntaxError::LonelyInverse, loc.clone(), tokens.clone()))?;
+                loc = tokens[j].1.clone();
+
+                tokens[i] = (
+                    Token::Inverse(Box::new(tokens[j].0.clone()), loc.clone()),
+                    loc,
+                );
+                tokens.remove(j);
             }
+            Token::Under(f, _) if matches!(f.0, Token::InvalidState) => {
+                if i + 2 >= tokens.len() {
+                    Err((
+                        SyntaxError::LonelyUnder,
+                        tokens[i].1.clone(),
+                        tokens.clone(),
+                    ))?;
+                }
 
-            loc = tokens[j].1.clone();
+                let mut loc = tokens[i].1.clone();
+                loc.end = tokens[i + 2].1.end;
+                let g = tokens.remove(i + 2);
+                let f = tokens.remove(i + 1);
 
-            tokens[i] = (
-                Token::Inverse(Box::new(tokens[j].0.clone()), loc.clone()),
-                loc,
+                tokens[i] = (Token::Under(Box::new(f), Box::new(g)), loc);
+            }
+            _ => {}
+        }
+    }
+
+    // Have definitions take their name and body
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Token::_Arrow = tokens[i].0 {
+            let arrow = tokens[i].1.clone();
+            if i == 0 || i + 1 >= tokens.len() {
+                Err((
+                    SyntaxError::InvalidDefinition,
+                    arrow.clone(),
+                    tokens.clone(),
+                ))?;
+            }
+
+            // Builtins are looked up first, so one by the same name would
+            // never be called
+            let name = |c: &char| c.is_alphabetic() && !BUILTINS.contains_key(c);
+            let (name, inverse) = match &tokens[i - 1].0 {
+                Token::FunctionCall(c) if name(c) => (*c, false),
+                Token::Inverse(tok, _) => match tok.as_ref() {
+                    Token::FunctionCall(c) if name(c) => (*c, true),
+                    _ => Err((
+                        SyntaxError::InvalidDefinition,
+                        arrow.clone(),
+                        tokens.clone(),
+                    ))?,
+                },
+                _ => Err((
+                    SyntaxError::InvalidDefinition,
+                    arrow.clone(),
+                    tokens.clone(),
+                ))?,
+            };
+            let Token::Function(body) = tokens[i + 1].0.clone() else {
+                Err((
+                    SyntaxError::InvalidDefinition,
+                    arrow.clone(),
+                    tokens.clone(),
+                ))?
+            };
+
+            let mut loc = tokens[i - 1].1.clone();
+            loc.end = tokens[i + 1].1.end;
+            tokens.splice(
+                i - 1..=i + 1,
+                [(Token::Definition(name, inverse, body), loc)],
             );
-            tokens.remove(j);
+            continue;
         }
         i += 1;
     }
 
     Ok(tokens)
 }
+
+/// Takes a block comment `#{ … }#` from `chars`, right after its `#`, up to
+/// where it ends, the ones nested in it included. `each` is given every char
+/// taken.
+///
+/// Returns false when the comment never ends, everything being taken.
+fn block_comment(
+    chars: &mut Peekable<impl Iterator<Item = char>>,
+    mut each: impl FnMut(char),
+) -> bool {
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        each(c);
+
+        match c {
+            '{' if depth == 0 => depth += 1,
+            '#' if chars.peek() == Some(&'{') => {
+                each('{');
+                chars.next();
+                depth += 1;
+            }
+            '}' if chars.peek() == Some(&'#') => {
+                each('#');
+                chars.next();
+                depth -= 1;
+
+                if depth == 0 {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Skips over a comment, the leading `#` must already be consumed.
+/// Returns the skipped text.
+///
+/// `# ...` runs until the end of the line, the newline itself is left for
+/// the caller. \
+/// `#{ ... }#` is a block comment, and can be nested.
+pub(crate) fn skip_comment(
+    chars: &mut Peekable<Chars>,
+    loc: &mut Loc,
+) -> Result<String, SyntaxError> {
+    let mut text = String::new();
+
+    if let Some('{') = chars.peek() {
+        let terminated = block_comment(chars, |c| {
+            text.push(c);
+            loc.end += c.len_utf8();
+            loc.column += 1;
+            if c == '\n' {
+                loc.line += 1;
+                loc.column = 0;
+            }
+        });
+
+        if !terminated {
+            return Err(SyntaxError::UnterminatedComment);
+        }
+        return Ok(text);
+    }
+
+    while let Some(c) = chars.peek() {
+        if *c == '\n' {
+            break;
+        }
+        text.push(*c);
+        loc.end += c.len_utf8();
+        loc.column += 1;
+        chars.next();
+    }
+
+    Ok(text)
+}
@@ -0,0 +1,150 @@
+use crate::{builtins::BUILTINS, formatter::SYMBOLS};
+
+/// Glyphs looking like, or meaning the same as in APL, glyphs of kuhi
+const SIMILAR: &[(char, &str)] = &[
+    ('x', "×"),
+    ('X', "×"),
+    ('*', "×ⁿ"),
+    ('·', "×"),
+    ('⋅', "×"),
+    ('∗', "×"),
+    ('/', "÷"),
+    ('^', "ⁿ∧"),
+    ('v', "∨▽"),
+    ('V', "∨▽"),
+    ('o', "◯"),
+    ('O', "◯"),
+    ('○', "◯"),
+    ('~', "⁻"),
+    ('¯', "⁻"),
+    ('−', "-"),
+    ('=', "←"),
+    ('<', "←"),
+    ('@', "⊡"),
+    ('⍳', "ι"),
+    ('⍴', "↯△"),
+    ('ρ', "↯△"),
+    ('⌽', "⇌↻"),
+    ('⊖', "⇌"),
+    ('⍟', "ₙ"),
+    ('∈', "∊"),
+    ('ϵ', "∊"),
+    ('⍪', "∾"),
+    ('∪', "⍷"),
+    ('⋀', "∧"),
+    ('⋁', "∨"),
+    ('⊃', "⊢"),
+    ('⍨', "↔"),
+];
+
+/// # Did you mean
+///
+/// What `glyph`, which is not a function, may have been meant as, `word`
+/// being the ASCII letters around it in the source, see `word_at`.
+///
+/// ASCII names of builtins close to `word` come first, then glyphs looking
+/// like `glyph`, at most 3 of them.
+///
+/// ## Examples
+///
+/// ```rust
+/// use kuhi::suggest::did_you_mean;
+///
+/// assert_eq!(
+///     did_you_mean('r', "sqr").unwrap(),
+///     "did you mean `sqrt` → `√`?"
+/// );
+/// assert_eq!(did_you_mean('x', "x").unwrap(), "did you mean `×`?");
+/// assert!(did_you_mean('f', "f").is_none());
+/// ```
+pub fn did_you_mean(glyph: char, word: &str) -> Option<String> {
+    // Short names are all close to each other, so they need to match
+    let max = match word.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    };
+    let closest = SYMBOLS
+        .iter()
+        .map(|(name, _)| distance(word, name))
+        .min()
+        .filter(|closest| *closest <= max);
+    let names = SYMBOLS
+        .iter()
+        .filter(|(name, _)| Some(distance(word, name)) == closest)
+        .map(|&(name, to)| (Some(name), to));
+
+    let glyphs = SIMILAR
+        .iter()
+        .filter(|(from, _)| *from == glyph)
+        .flat_map(|(_, to)| to.split_inclusive(|_| true))
+        .filter(|to| exists(to))
+        .map(|to| (None, to));
+
+    let mut suggestions: Vec<(Option<&str>, &str)> = vec![];
+    for (name, to) in names.chain(glyphs) {
+        if suggestions.len() < 3 && !suggestions.iter().any(|(_, seen)| *seen == to) {
+            suggestions.push((name, to));
+        }
+    }
+
+    let suggestions = suggestions
+        .into_iter()
+        .map(|(name, to)| match name {
+            Some(name) => format!("`{name}` → `{to}`"),
+            None => format!("`{to}`"),
+        })
+        .collect::<Vec<_>>();
+
+    match suggestions.as_slice() {
+        [] => None,
+        [only] => Some(format!("did you mean {only}?")),
+        [rest @ .., last] => Some(format!("did you mean {} or {last}?", rest.join(", "))),
+    }
+}
+
+/// The ASCII letters around `at` in `source`, or the character at `at` when
+/// it isn't one
+pub fn word_at(source: &str, at: usize) -> &str {
+    let Some(c) = source.get(at..).and_then(|rest| rest.chars().next()) else {
+        return "";
+    };
+    if !c.is_ascii_alphabetic() {
+        return &source[at..at + c.len_utf8()];
+    }
+
+    let letter = |c: char| c.is_ascii_alphabetic();
+    let start = source[..at].trim_end_matches(letter).len();
+    let end = source.len() - source[at..].trim_start_matches(letter).len();
+
+    &source[start..end]
+}
+
+/// Whether `glyph` means something in kuhi, as a builtin or as syntax
+fn exists(glyph: &str) -> bool {
+    let mut chars = glyph.chars();
+    let builtin = match (chars.next(), chars.next()) {
+        (Some(c), None) => BUILTINS.contains_key(&c) || c == '-',
+        _ => false,
+    };
+
+    builtin || SYMBOLS.iter().any(|(_, to)| *to == glyph)
+}
+
+/// Levenshtein distance, counting characters
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(x != *y);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
@@ -1,12 +1,18 @@
-use std::{fmt::Display, ops::*};
+use std::{cmp::Ordering, fmt::Display, ops::*};
 
-use rug::{float::Constant as consts, ops::Pow, Complex, Float, Integer, Rational};
+use rug::{
+    float::{Constant as consts, Special},
+    ops::Pow,
+    Complex, Float, Integer, Rational,
+};
 
 use lazy_static::lazy_static;
 
 use crate::{
+    array::Array,
     builtins::{RuntimeResult, Stack},
     err::RuntimeError,
+    limits,
     parser::Token,
 };
 
@@ -25,6 +31,8 @@ pub enum Value {
     String(String),
 
     List(Vec<Value>),
+    Tuple(Vec<Value>),
+    Array(Array),
 
     Scope(Stack),
     Function(fn(&mut Stack) -> RuntimeResult),
@@ -54,6 +62,12 @@ impl Value {
                 v.append(&mut self.validate_list().unwrap());
                 v
             }
+            Value::Tuple(_) => vec!["Tuple".to_string()],
+            Value::Array(a) => {
+                let mut v = vec!["Array".to_string()];
+                v.append(&mut Value::List(a.data.clone()).validate_list().unwrap());
+                v
+            }
 
             Value::Scope(_) => vec![],
             Value::Function(_) => vec!["Function".to_string()],
@@ -121,6 +135,9 @@ impl Value {
                 Value::List(res)
             }
             (Value::Integer(n), Value::Integer(m)) => {
+                if let Err(err) = limits::bits(__pow_bits(n.significant_bits(), m)) {
+                    return Value::InvalidState(err);
+                }
                 if m.clone().signum() == -1 {
                     return Value::Rational(Rational::from((
                         1,
@@ -139,6 +156,13 @@ impl Value {
                 }
             }
             (Value::Rational(r), Value::Integer(n)) => {
+                let bits = r
+                    .numer()
+                    .significant_bits()
+                    .max(r.denom().significant_bits());
+                if let Err(err) = limits::bits(__pow_bits(bits, n)) {
+                    return Value::InvalidState(err);
+                }
                 if n.clone().signum() == -1 {
                     return Value::Rational(
                         Rational::from((1, 1))
@@ -173,6 +197,12 @@ impl Value {
                     }
                 }
             }
+            (x, y)
+                if x.types().contains(&"Number".to_string())
+                    && y.types().contains(&"Number".to_string()) =>
+            {
+                x.pow_approx(y)
+            }
             _ => {
                 return Value::InvalidState(RuntimeError::TypeMissmatch {
                     expected: "Number".to_string(),
@@ -194,9 +224,13 @@ impl Value {
         }
     }
 
-    pub fn root(&self, rhs: &Self) -> Self {
+    /// The `n`th root, kept exact for perfect powers of integers and rationals.
+    pub fn root(&self, n: &Self) -> Self {
+        if let Value::List(vals) = self {
+            return Value::List(vals.iter().map(|val| val.root(n)).collect());
+        }
         if !self.types().contains(&"Number".to_string())
-            || !rhs.types().contains(&"Number".to_string())
+            || !n.types().contains(&"Number".to_string())
         {
             return Value::InvalidState(RuntimeError::TypeMissmatch {
                 expected: "Number".to_string(),
@@ -207,7 +241,71 @@ impl Value {
                         .rev()
                         .collect::<Vec<_>>()
                         .join(" ⊂ "),
-                    rhs.types()
+                    n.types().into_iter().rev().collect::<Vec<_>>().join(" ⊂ ")
+                ),
+            });
+        }
+        if n.is_zero() {
+            return Value::InvalidState(RuntimeError::ZerothRoot);
+        }
+
+        // Negative roots are roots of the reciprocal
+        if let Value::Integer(k) = n {
+            if k.is_negative() {
+                return match self.reciprocal() {
+                    err @ Value::InvalidState(_) => err,
+                    recip => recip.root(&Value::Integer(-k.clone())),
+                };
+            }
+        }
+
+        if let (Some(x), Value::Integer(k)) = (self.exact(), n) {
+            match k.to_u32() {
+                // Even roots of negative numbers are complex
+                Some(k) if !(x.is_negative() && k % 2 == 0) => {
+                    let (numer, denom) = x.into_numer_denom();
+                    let (numer, numer_rem) = numer.root_rem(Integer::new(), k);
+                    let (denom, denom_rem) = denom.root_rem(Integer::new(), k);
+
+                    if numer_rem.is_zero() && denom_rem.is_zero() {
+                        return match denom == 1 {
+                            true => Value::Integer(numer),
+                            false => Value::Rational(Rational::from((numer, denom))),
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.pow_approx(&n.reciprocal())
+    }
+
+    /// The logarithm in base `base`, kept exact when `self` is an integer
+    /// power of it.
+    ///
+    /// The logarithm of 0, or ε, is ⁻∞ in bases above 1 and ∞ in those between
+    /// 0 and 1, that of ∞ the other way around. Both are Undefined in other
+    /// bases, as are logarithms in base 0. Other negative numbers and bases
+    /// take the principal complex logarithm, unless an integer power of the
+    /// base gives the number, like ⁻8 in base ⁻2.
+    pub fn log(&self, base: &Self) -> Self {
+        if let Value::List(vals) = self {
+            return Value::List(vals.iter().map(|val| val.log(base)).collect());
+        }
+        if !self.types().contains(&"Number".to_string())
+            || !base.types().contains(&"Number".to_string())
+        {
+            return Value::InvalidState(RuntimeError::TypeMissmatch {
+                expected: "Number".to_string(),
+                got: format!(
+                    "{}` and `{}",
+                    self.types()
+                        .into_iter()
+                        .rev()
+                        .collect::<Vec<_>>()
+                        .join(" ⊂ "),
+                    base.types()
                         .into_iter()
                         .rev()
                         .collect::<Vec<_>>()
@@ -216,15 +314,68 @@ impl Value {
             });
         }
 
-        match (self, rhs) {
-            (Value::Integer(n), Value::Integer(m)) => {
-                println!("{} {}", n, m);
-                Value::Integer(n.clone().root(m.to_u32_wrapping()))
+        if matches!(self, Value::Undefined) || matches!(base, Value::Undefined) {
+            return Value::Undefined;
+        }
+        if let (Some(x), Some(b)) = (self.exact(), base.exact()) {
+            if b == 1 {
+                return Value::InvalidState(RuntimeError::DivideByZero);
             }
-            _ => {
-                todo!()
+            if !x.is_zero() && !b.is_zero() {
+                // Only an estimate, it is checked right after
+                let k = (self.approx(128).abs().ln() / base.approx(128).abs().ln()).round();
+                let k = k.to_i32_saturating().unwrap_or(0);
+                if b.pow(k) == x {
+                    return Value::Integer(Integer::from(k));
+                }
             }
         }
+        // ln 0 is ⁻∞ and ln ∞ is ∞, which complex numbers can't hold, and ε is
+        // as good as 0
+        let zero = |x: &Value| x.is_zero() || matches!(x, Value::Epsilon(_));
+        if zero(base) {
+            return Value::Undefined;
+        }
+        // The sign of ln `base`, when it is real and finite
+        let sign = match base {
+            Value::Complex(_) | Value::Infinity(_) => None,
+            base => match base.approx(128) {
+                b if b > 1 => Some(1),
+                b if b.is_sign_positive() && b < 1 => Some(-1),
+                _ => None,
+            },
+        };
+        match (self, sign) {
+            (x, Some(sign)) if zero(x) => return Value::Infinity(-sign),
+            (Value::Infinity(1), Some(sign)) => return Value::Infinity(sign),
+            (x, _) if zero(x) || matches!(x, Value::Infinity(_)) => return Value::Undefined,
+            _ => {}
+        }
+
+        let log = self.complex_approx(128).ln() / base.complex_approx(128).ln();
+        Value::from_complex(log)
+    }
+
+    /// Powers that can't be kept exact, going through complex numbers so
+    /// negative bases work too.
+    fn pow_approx(&self, exp: &Self) -> Self {
+        let pow = self.complex_approx(128).pow(exp.complex_approx(128));
+        Value::from_complex(pow)
+    }
+
+    fn complex_approx(&self, prec: u32) -> Complex {
+        match self {
+            Value::Complex(z) => Complex::with_val(prec, z),
+            x => Complex::with_val(prec, x.approx(prec)),
+        }
+    }
+
+    /// Real results are kept as floats
+    fn from_complex(z: Complex) -> Self {
+        match z.imag().is_zero() {
+            true => Value::Float(z.real().clone()),
+            false => Value::Complex(z),
+        }
     }
 
     pub fn is_zero(&self) -> bool {
@@ -276,6 +427,19 @@ impl Value {
         v
     }
 
+    /// The error of the first `InvalidState` in this value, looking into
+    /// lists and tuples, as steps on them work element by element.
+    pub fn error(&self) -> Option<&RuntimeError> {
+        match self {
+            Value::InvalidState(err) => Some(err),
+            Value::List(vals) | Value::Tuple(vals) => vals.iter().find_map(Value::error),
+            Value::Array(array) => array.data.iter().find_map(Value::error),
+            _ => None,
+        }
+    }
+
+    /// Tuples are heterogeneous on purpose, so they may differ from each
+    /// other, but not from the other elements.
     pub fn validate_list(&self) -> Result<Vec<String> /* Type */, RuntimeError> {
         let mut inner_type: Vec<String> = Vec::with_capacity(2);
 
@@ -285,6 +449,11 @@ impl Value {
             vals.clone()
                 .into_iter()
                 .try_reduce(|acc, val| match (acc, val) {
+                    (Tuple(_), val @ Tuple(_)) => Ok(val),
+                    (a @ Tuple(_), b) | (a, b @ Tuple(_)) => Err(RuntimeError::ListTypeMissmatch {
+                        first: a.types().join(", "),
+                        second: b.types().join(", "),
+                    }),
                     (List(a), List(b)) => {
                         if a.len() != b.len() {
                             return Err(RuntimeError::ListElementSizeMissmatch {
@@ -388,6 +557,96 @@ impl From<Token> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Integer(Integer::from(n))
+    }
+}
+
+impl From<Integer> for Value {
+    fn from(n: Integer) -> Self {
+        Value::Integer(n)
+    }
+}
+
+impl From<Rational> for Value {
+    fn from(r: Rational) -> Self {
+        match r.is_integer() {
+            true => Value::Integer(r.numer().clone()),
+            false => Value::Rational(r),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Float(Float::with_val(128, x))
+    }
+}
+
+impl TryFrom<Value> for Integer {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer(n) => Ok(n),
+            Value::Rational(r) if r.is_integer() => Ok(r.numer().clone()),
+            otherwise => Err(RuntimeError::TypeMissmatch {
+                expected: "Integer".to_string(),
+                got: otherwise.types().join(", "),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let n = Integer::try_from(value)?;
+        n.to_i64().ok_or(RuntimeError::TypeMissmatch {
+            expected: "Integer fitting in 64 bits".to_string(),
+            got: "Integer".to_string(),
+        })
+    }
+}
+
+impl TryFrom<Value> for Rational {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer(n) => Ok(Rational::from(n)),
+            Value::Rational(r) => Ok(r),
+            otherwise => Err(RuntimeError::TypeMissmatch {
+                expected: "Rational".to_string(),
+                got: otherwise.types().join(", "),
+            }),
+        }
+    }
+}
+
+/// Rounds exact values, like `π` or `1/3`, to the nearest float
+impl TryFrom<Value> for f64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer(_)
+            | Value::Rational(_)
+            | Value::Float(_)
+            | Value::Pi(..)
+            | Value::E(..)
+            | Value::Infinity(_) => Ok(value.approx(53).to_f64()),
+            Value::Epsilon(_) => Ok(0.0),
+            otherwise => Err(RuntimeError::TypeMissmatch {
+                expected: "real Number".to_string(),
+                got: otherwise.types().join(", "),
+            }),
+        }
+    }
+}
+
 impl Add for Value {
     type Output = Self;
 
@@ -481,6 +740,10 @@ impl Add for Value {
             // (E(r, exp), Integer(n)) | (Integer(n), E(r, exp)) => Rational(n * r),
             // (E(r, exp), Rational(s)) | (Rational(s), E(r, exp)) => Rational(s * r),
             // (E(r, exp), Complex(c)) | (Complex(c), E(r, exp)) => Complex(c * r * E),
+            (a @ Array(_), b) | (a, b @ Array(_)) => {
+                crate::array::Array::broadcast(a, b, |a, b| a + b)
+            }
+
             (List(a), List(b)) => {
                 if a.len() != b.len() {
                     return InvalidState(RuntimeError::ListElementSizeMissmatch {
@@ -538,6 +801,10 @@ impl Neg for Value {
             Undefined => Undefined,
 
             List(vals) => List(vals.into_iter().map(|val| -val).collect()),
+            Array(mut a) => {
+                a.data = a.data.into_iter().map(|val| -val).collect();
+                Array(a)
+            }
 
             invalid => InvalidState(RuntimeError::TypeMissmatch {
                 expected: "Numeric".to_string(),
@@ -561,6 +828,19 @@ impl Mul for Value {
     fn mul(self, rhs: Self) -> Self::Output {
         use Value::*;
 
+        if !self.types().contains(&"Number".to_string())
+            || !rhs.types().contains(&"Number".to_string())
+        {
+            return InvalidState(RuntimeError::TypeMissmatch {
+                expected: "Number".to_string(),
+                got: format!(
+                    "{}` and `{}",
+                    self.types().join(", "),
+                    rhs.types().join(", ")
+                ),
+            });
+        }
+
         match (self, rhs) {
             (Integer(n), Integer(m)) => Integer(n * m),
             (Rational(r), Rational(s)) => Rational(r * s),
@@ -639,6 +919,10 @@ impl Mul for Value {
             // (E(r, exp), Integer(n)) | (Integer(n), E(r, exp)) => Rational(n * r),
             // (E(r, exp), Rational(s)) | (Rational(s), E(r, exp)) => Rational(s * r),
             // (E(r, exp), Complex(c)) | (Complex(c), E(r, exp)) => Complex(c * r * E),
+            (a @ Array(_), b) | (a, b @ Array(_)) => {
+                crate::array::Array::broadcast(a, b, |a, b| a * b)
+            }
+
             (List(a), List(b)) => {
                 if a.len() != b.len() {
                     return InvalidState(RuntimeError::ListElementSizeMissmatch {
@@ -676,6 +960,185 @@ impl Mul for Value {
     }
 }
 
+/// # Ordering
+///
+/// Values are totally ordered so lists can be sorted and searched.
+///
+/// Numbers are compared exactly by value, so `2 == 2/1` and `π` falls between
+/// `3.14` and `3.15`. Complex numbers are ordered by their real part, then by
+/// their imaginary part. A `NaN` float goes after every other number, and
+/// `Undefined` after that.
+///
+/// Different kinds of values go in this order: numbers, lists and arrays,
+/// tuples, strings, then everything else. Lists and tuples are compared
+/// element by element, a shorter one going first when it's a prefix.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.kind_order(), other.kind_order());
+        if a != b {
+            return a.cmp(&b);
+        }
+
+        match (self, other) {
+            (Value::List(_) | Value::Array(_), Value::List(_) | Value::Array(_)) => {
+                let (Value::List(a), Value::List(b)) = (self.as_list(), other.as_list()) else {
+                    unreachable!()
+                };
+                a.cmp(&b)
+            }
+            (Value::Tuple(a), Value::Tuple(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (a, b) if a.kind_order() == 0 => Value::cmp_numbers(a, b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl Value {
+    fn kind_order(&self) -> u8 {
+        match self {
+            Value::List(_) | Value::Array(_) => 1,
+            Value::Tuple(_) => 2,
+            Value::String(_) => 3,
+            Value::Scope(_) | Value::Function(_) => 4,
+            Value::InvalidState(_) => 5,
+            _ => 0,
+        }
+    }
+
+    /// Arrays are compared as the nested lists they stand for.
+    fn as_list(&self) -> Value {
+        match self {
+            Value::Array(a) => a.clone().into_list(),
+            list => list.clone(),
+        }
+    }
+
+    fn cmp_numbers(a: &Value, b: &Value) -> Ordering {
+        use Value::*;
+
+        let is_nan = |v: &Value| match v {
+            Float(x) => x.is_nan(),
+            Complex(z) => z.real().is_nan() || z.imag().is_nan(),
+            _ => false,
+        };
+
+        match (a, b) {
+            (Undefined, Undefined) => Ordering::Equal,
+            (Undefined, _) => Ordering::Greater,
+            (_, Undefined) => Ordering::Less,
+
+            (a, b) if is_nan(a) || is_nan(b) => is_nan(a).cmp(&is_nan(b)),
+
+            (Complex(z), w) | (w, Complex(z)) => {
+                let (re, im) = (Float(z.real().clone()), Float(z.imag().clone()));
+                let (w_re, w_im) = match w {
+                    Complex(w) => (Float(w.real().clone()), Float(w.imag().clone())),
+                    w => (w.clone(), Integer(rug::Integer::new())),
+                };
+                let ord = Value::cmp_numbers(&re, &w_re).then(Value::cmp_numbers(&im, &w_im));
+
+                // The pattern may have swapped the sides
+                if let Complex(_) = a {
+                    ord
+                } else {
+                    ord.reverse()
+                }
+            }
+
+            (Infinity(x), Infinity(y)) => x.signum().cmp(&y.signum()),
+            (Infinity(x), _) => x.signum().cmp(&0),
+            (_, Infinity(y)) => 0.cmp(&y.signum()),
+
+            // Infinitesimals are only distinguishable from 0
+            (Epsilon(x), Epsilon(y)) => x.signum().cmp(&y.signum()),
+            (Epsilon(x), b) if b.is_zero() => x.signum().cmp(&0),
+            (a, Epsilon(y)) if a.is_zero() => 0.cmp(&y.signum()),
+            (Epsilon(_), b) => Value::cmp_numbers(&Integer(rug::Integer::new()), b),
+            (a, Epsilon(_)) => Value::cmp_numbers(a, &Integer(rug::Integer::new())),
+
+            (a, b) => match (a.exact(), b.exact()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(r), None) => match b {
+                    Float(x) => x.partial_cmp(&r).unwrap().reverse(),
+                    _ => Value::cmp_approx(a, b),
+                },
+                (None, Some(r)) => match a {
+                    Float(x) => x.partial_cmp(&r).unwrap(),
+                    _ => Value::cmp_approx(a, b),
+                },
+                (None, None) => match (a, b) {
+                    (Float(x), Float(y)) => x.partial_cmp(y).unwrap(),
+                    (Pi(r, e), Pi(s, f)) if e == f => r.cmp(s),
+                    _ => Value::cmp_approx(a, b),
+                },
+            },
+        }
+    }
+
+    /// The value of a real number, when it is rational.
+    fn exact(&self) -> Option<Rational> {
+        match self {
+            Value::Integer(n) => Some(Rational::from(n)),
+            Value::Rational(r) => Some(r.clone()),
+            Value::Pi(r, _) | Value::E(r, _) if r.is_zero() => Some(Rational::new()),
+            Value::E(r, 0) => Some(r.clone()),
+            _ => None,
+        }
+    }
+
+    /// Compares numbers where at least one is irrational, so they can't be
+    /// equal unless both are zero, and raising the precision is enough to
+    /// tell them apart.
+    fn cmp_approx(a: &Value, b: &Value) -> Ordering {
+        let mut prec = 128;
+        while prec <= 1 << 16 {
+            let (x, y) = (a.approx(prec), b.approx(prec));
+            let diff = Float::with_val(prec, &x - &y);
+            let error = (x.abs() + y.abs()) >> (prec as i32 - 8);
+
+            if diff.clone().abs() > error {
+                return diff.cmp0().unwrap();
+            }
+            prec *= 4;
+        }
+
+        Ordering::Equal
+    }
+
+    fn approx(&self, prec: u32) -> Float {
+        match self {
+            Value::Integer(n) => Float::with_val(prec, n),
+            Value::Rational(r) => Float::with_val(prec, r),
+            Value::Float(x) => Float::with_val(prec, x),
+            Value::Pi(r, e) => {
+                let pi = Float::with_val(prec, consts::Pi);
+                match e {
+                    1 => pi * r,
+                    _ => Float::with_val(prec, r) / pi,
+                }
+            }
+            Value::E(r, e) => Float::with_val(prec, *e).exp() * r,
+            Value::Infinity(sign) => Float::with_val(prec, Special::Infinity) * *sign,
+            _ => Float::with_val(prec, 0),
+        }
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Value::*;
@@ -701,6 +1164,15 @@ impl Display for Value {
                     .collect::<Vec<_>>()
                     .join(" ")
             ),
+            Array(a) => write!(f, "{}", a),
+            Tuple(vals) => write!(
+                f,
+                "⟨{}⟩",
+                vals.iter()
+                    .map(|val| val.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
 
             Scope(_) => write!(f, "<scope>"),
             Function(_) => write!(f, "<function>"),
@@ -751,3 +1223,9 @@ impl Display for Value {
         }
     }
 }
+
+/// At least how many bits a number of `bits` bits has once raised to `exp`
+fn __pow_bits(bits: u32, exp: &Integer) -> u64 {
+    let exp = exp.clone().abs().to_u64().unwrap_or(u64::MAX);
+    u64::from(bits.saturating_sub(1)).saturating_mul(exp)
+}
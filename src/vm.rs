@@ -1,169 +1,509 @@
-use std::fmt::Display;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use crate::{
-    builtins::BUILTINS,
-    err::RuntimeError,
+    builtins::{Builtin, CustomBuiltin, Signature, Stack, BUILTINS},
+    bytecode::{compile, Instr, Program},
+    err::{Context, Frame, FrameKind, RuntimeError, StackValue, Warning},
+    limits::{self, Limits},
     parser::{Loc, Token},
     value::Value,
+    warnings,
 };
 
 /// Runtime
 
 pub struct Env {
     pub stack: Vec<Value>,
+    pub functions: HashMap<char, UserFunction>,
+    /// Registered with `Env::register`, see there for how calls are looked up
+    pub builtins: HashMap<char, Rc<CustomBuiltin>>,
+    /// Checked while running, see `Limits`
+    pub limits: Limits,
+    /// Stops the program at its next step once set, from any thread
+    pub cancel: Arc<AtomicBool>,
+    /// Found by builtins while running, once per step, kept until taken.
+    /// `None` doesn't look for them, which is the default
+    pub warnings: Option<Vec<(Warning, Loc)>>,
 
     tokens: Vec<(Token, Loc)>,
+    /// The bodies of user functions, and of their inverses, once ran
+    pub(crate) compiled: HashMap<(char, bool), Rc<Program>>,
+    /// Ran in the current `Env::run`
+    steps: u64,
+    /// Of the calls being ran, see `Limits::depth`
+    depth: usize,
+    /// Of the current error, see `Context`
+    context: Context,
+    /// What the current builtin was given, reused from step to step, see
+    /// `Env::keep`
+    kept: Vec<StackValue>,
+    /// How many of `kept` the current builtin was given
+    kept_len: usize,
+}
+
+/// A function defined with `f ← (…)`, and the inverse given with `⁻¹f ← (…)`
+#[derive(Clone, Default)]
+pub struct UserFunction {
+    pub body: Option<Vec<(Token, Loc)>>,
+    pub inverse: Option<Vec<(Token, Loc)>>,
 }
 
 impl Env {
     pub fn new(tokens: Vec<(Token, Loc)>) -> Self {
         Self {
             stack: Vec::new(),
+            functions: HashMap::new(),
+            builtins: HashMap::new(),
+            limits: Limits::default(),
+            cancel: Arc::new(AtomicBool::new(false)),
+            warnings: None,
             tokens,
+            compiled: HashMap::new(),
+            steps: 0,
+            depth: 0,
+            context: Context::default(),
+            kept: Vec::new(),
+            kept_len: 0,
         }
     }
 
+    /// Registers a builtin from the host, replacing the one registered with
+    /// the same glyph before.
+    ///
+    /// Calling `c` looks it up in order, so registered builtins shadow the
+    /// default ones, and both shadow user functions:
+    ///
+    /// | Looked up in              | Defined by                       |
+    /// |---------------------------|----------------------------------|
+    /// | `Env::builtins`           | the host, with `Env::register`   |
+    /// | `builtins::BUILTINS`      | kuhi                             |
+    /// | `Env::functions`          | the program, with `c ← (…)`      |
+    pub fn register(&mut self, glyph: char, builtin: CustomBuiltin) -> &mut Self {
+        self.builtins.insert(glyph, Rc::new(builtin));
+        // They are looked up when compiling
+        self.compiled.clear();
+
+        self
+    }
+
+    /// The ASCII names of registered builtins, and their glyph
+    pub fn aliases(&self) -> Vec<(String, char)> {
+        self.builtins
+            .iter()
+            .filter_map(|(c, builtin)| Some((builtin.name.clone()?, *c)))
+            .collect()
+    }
+
     pub fn repurpose(&mut self, tokens: &Vec<(Token, Loc)>) -> &mut Self {
         self.tokens = tokens.clone();
 
         self
     }
 
-    pub fn run(&mut self) -> Result<(), (RuntimeError, Loc)> {
-        for (token, loc) in self.tokens.iter().rev() {
-            let stack = &mut self.stack;
-            match token {
-                Token::Spacing => continue,
-                Token::Integer(n) => stack.push(Value::Integer(n.clone())),
-                Token::Rational(r) => stack.push(Value::Rational(r.clone())),
-                Token::Complex(c) => stack.push(Value::Complex(c.clone())),
-
-                Token::List(vals) => stack.push(Value::List(
-                    vals.into_iter()
-                        .map(|tok| tok.clone().into())
-                        .collect::<Vec<_>>(),
-                )),
-                Token::_UnfinishedList(_) => unreachable!("Unfinished list"),
-
-                Token::Scope(op) => {
-                    let top = if let Some(value) = stack.pop() {
-                        value
-                    } else {
-                        return Err((
-                            RuntimeError::InvalidPop {
-                                len: stack.len(),
-                                arity: 1,
-                            },
-                            loc.clone(),
-                        ));
-                    };
-
-                    match top {
-                        Value::List(vals) => {
-                            let mut inner_env = Env::new(op.to_vec());
-                            inner_env.stack = vals;
-                            inner_env.run()?;
-
-                            match inner_env.stack.len() {
-                                0 => continue,
-                                1 => stack.push(inner_env.stack.pop().unwrap()),
-                                _ => stack.push(Value::List(inner_env.stack)),
-                            }
-                        }
-                        otherwise => stack.push(otherwise),
-                    }
-                }
+    /// Runs the tokens on the stack, failing with the calls the error
+    /// happened in and what was on the stack, see `Context`
+    pub fn run(&mut self) -> Result<(), (RuntimeError, Loc, Box<Context>)> {
+        // Definitions are hoisted, so they can be used before (on the right of)
+        // where they are written
+        if Env::define(&mut self.functions, &self.tokens) {
+            self.compiled.clear();
+        }
+
+        let program = compile(&self.tokens, &self.builtins)
+            .map_err(|(err, loc)| (err, loc, Box::default()))?;
+        let mut stack = std::mem::take(&mut self.stack);
+        let _limits = limits::enter(self.limits);
+        self.steps = 0;
+        self.depth = 0;
+        self.context = Context::default();
+        let result = self.exec(&program, &mut stack);
+        self.stack = stack;
+
+        result.map_err(|(err, loc)| (err, loc, Box::new(std::mem::take(&mut self.context))))
+    }
+
+    /// The VM loop, running `program` on `stack`
+    fn exec(&mut self, program: &Program, stack: &mut Stack) -> Result<(), (RuntimeError, Loc)> {
+        for (instr, loc) in program.code.iter().zip(&program.spans) {
+            self.steps += 1;
+            if let Some(max) = self.limits.steps.filter(|max| self.steps > *max) {
+                return Err((RuntimeError::StepLimit(max), loc.clone()));
+            }
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err((RuntimeError::Cancelled, loc.clone()));
+            }
 
-                Token::Infinity => stack.push(Value::Infinity(1)),
-                Token::Epsilon => stack.push(Value::Epsilon(1)),
-                Token::Pi(r) => stack.push(Value::Pi(r.clone(), 1)),
-                Token::E(r, pow) => stack.push(Value::E(r.clone(), *pow)),
+            // Builtins take their values off the stack even when they fail,
+            // so what they are given is kept beforehand
+            let inputs = match instr {
+                Instr::Builtin(builtin)
+                | Instr::Inverse(builtin)
+                | Instr::RightInverse(builtin) => Some(builtin.signature().inputs),
+                Instr::Custom(builtin) => Some(builtin.signature.inputs),
+                Instr::CustomInverse(builtin) => Some(builtin.signature.outputs.unwrap_or(0)),
+                Instr::Minus => Some(1),
+                _ => None,
+            };
+            if let Some(inputs) = inputs {
+                self.keep(stack, inputs);
+            }
+            // Only cloned when looking for warnings
+            let base = stack.len().saturating_sub(inputs.unwrap_or(0));
+            let given = match (&self.warnings, inputs) {
+                (Some(_), Some(_)) => stack[base..].to_vec(),
+                _ => vec![],
+            };
 
-                Token::Dup => {
-                    if let Some(value) = stack.pop() {
+            let result = match instr {
+                Instr::Push(i) => {
+                    stack.push(program.constants[*i].clone());
+                    Ok(())
+                }
+                Instr::Dup => match stack.last() {
+                    Some(value) => {
                         stack.push(value.clone());
-                        stack.push(value);
-                    } else {
-                        return Err((
-                            RuntimeError::InvalidPop {
-                                len: stack.len(),
-                                arity: 1,
-                            },
-                            loc.clone(),
-                        ));
+                        Ok(())
                     }
-                }
-                Token::Pop => {
-                    if let Some(_) = stack.pop() {
-                        // Do nothing
-                    } else {
-                        return Err((
-                            RuntimeError::InvalidPop {
-                                len: stack.len(),
-                                arity: 1,
-                            },
-                            loc.clone(),
-                        ));
+                    None => Err(RuntimeError::InvalidPop {
+                        len: stack.len(),
+                        arity: 1,
+                    }),
+                },
+                Instr::Pop => match stack.pop() {
+                    Some(_) => Ok(()),
+                    None => Err(RuntimeError::InvalidPop {
+                        len: stack.len(),
+                        arity: 1,
+                    }),
+                },
+                Instr::Minus => match stack.pop() {
+                    Some(value) => {
+                        stack.push(-value);
+                        Ok(())
                     }
+                    None => Err(RuntimeError::InvalidPop {
+                        len: stack.len(),
+                        arity: 1,
+                    }),
+                },
+
+                Instr::Builtin(builtin) => builtin.call(stack),
+                Instr::Inverse(builtin) => builtin.call_inverse(stack),
+                Instr::RightInverse(builtin) => builtin.call_right_inverse(stack),
+                Instr::Custom(builtin) => builtin.call(stack),
+                Instr::CustomInverse(builtin) => builtin.call_inverse(stack),
+
+                Instr::Call(c) => {
+                    let body = self.function(*c, false, loc)?;
+                    self.nested(&body, stack, loc)
+                        .map_err(|err| self.frame(err, FrameKind::Function(*c), loc))?;
+                    Ok(())
+                }
+                Instr::CallInverse(c) => {
+                    let body = self.function(*c, true, loc)?;
+                    self.nested(&body, stack, loc)
+                        .map_err(|err| self.frame(err, FrameKind::Inverse(*c), loc))?;
+                    Ok(())
                 }
-                Token::Flip => {
-                    match unsafe { BUILTINS.get(&'↔').unwrap_unchecked() }.call(stack.clone()) {
-                        Err(err) => return Err((err, loc.clone())),
-                        Ok(stack) => {
-                            self.stack = stack;
+
+                Instr::Scope(body) => match stack.pop() {
+                    Some(Value::List(vals)) => {
+                        let mut inner = vals;
+                        self.nested(body, &mut inner, loc)
+                            .map_err(|err| self.frame(err, FrameKind::Scope, loc))?;
+
+                        match inner.len() {
+                            0 => {}
+                            1 => stack.push(inner.pop().unwrap()),
+                            _ => stack.push(Value::Tuple(inner.into_iter().rev().collect())),
                         }
+                        Ok(())
+                    }
+                    Some(otherwise) => {
+                        stack.push(otherwise);
+                        Ok(())
                     }
+                    None => Err(RuntimeError::InvalidPop {
+                        len: stack.len(),
+                        arity: 1,
+                    }),
+                },
+                Instr::Tuple(body) => {
+                    let mut inner = vec![];
+                    self.nested(body, &mut inner, loc)
+                        .map_err(|err| self.frame(err, FrameKind::Tuple, loc))?;
+
+                    // Top of the stack first, so it reads like the source
+                    stack.push(Value::Tuple(inner.into_iter().rev().collect()));
+                    Ok(())
                 }
-                Token::Minus => {
-                    if let Some(value) = stack.pop() {
-                        stack.push(-value);
-                    } else {
-                        return Err((
-                            RuntimeError::InvalidPop {
-                                len: stack.len(),
-                                arity: 1,
-                            },
-                            loc.clone(),
-                        ));
+            };
+
+            let result = result.and_then(|()| match stack.last() {
+                // Only builtins and scopes build new values
+                Some(value) if !matches!(instr, Instr::Push(_) | Instr::Dup | Instr::Pop) => {
+                    match value.error() {
+                        Some(err) => Err(err.clone()),
+                        None => limits::check(value),
                     }
                 }
+                _ => Ok(()),
+            });
+            if let Err(err) = result {
+                match inputs {
+                    Some(_) => self.context.stack = self.kept[..self.kept_len].to_vec(),
+                    // The others leave the stack as it was when they fail
+                    None => self.snapshot(stack, 3),
+                }
+                return Err((err, loc.clone()));
+            }
+            if inputs.is_some() {
+                self.warn(&given, &stack[base.min(stack.len())..], loc);
+            }
+        }
 
-                Token::FunctionCall(c) => match BUILTINS.get(&c) {
-                    Some(builtin) => match builtin.call(stack.clone()) {
-                        Err(err) => return Err((err, loc.clone())),
-                        Ok(stack) => {
-                            self.stack = stack;
-                        }
-                    },
-                    None => return Err((RuntimeError::FunctionNotFound(c.clone()), loc.clone())),
-                },
+        Ok(())
+    }
+
+    /// Runs `program` one call deeper, failing at `loc` when too deep
+    fn nested(
+        &mut self,
+        program: &Program,
+        stack: &mut Stack,
+        loc: &Loc,
+    ) -> Result<(), (RuntimeError, Loc)> {
+        if let Some(max) = self.limits.depth.filter(|max| self.depth >= *max) {
+            return Err((RuntimeError::DepthLimit(max), loc.clone()));
+        }
 
-                Token::Function(_f) => {
-                    todo!("Implement function application")
+        self.depth += 1;
+        let result = self.exec(program, stack);
+        self.depth -= 1;
+
+        result
+    }
+
+    /// Keeps what a step lost going from `given` to `outputs`, see `Warning`
+    fn warn(&mut self, given: &[Value], outputs: &[Value], loc: &Loc) {
+        let Some(found) = &mut self.warnings else {
+            return;
+        };
+
+        for warning in warnings::check(given, outputs) {
+            // Steps in functions are ran many times, but written once
+            if !found
+                .iter()
+                .any(|(seen, at)| *seen == warning && at.start == loc.start)
+            {
+                found.push((warning, loc.clone()));
+            }
+        }
+    }
+
+    /// Keeps the top `n` values of `stack` in `kept`, in case the builtin
+    /// given them fails, reusing what the ones before were kept in
+    fn keep(&mut self, stack: &Stack, n: usize) {
+        let top = &stack[stack.len().saturating_sub(n)..];
+        for (i, value) in top.iter().enumerate() {
+            match self.kept.get_mut(i) {
+                Some(kept) => kept.retake(value),
+                None => self.kept.push(StackValue::new(value)),
+            }
+        }
+        self.kept_len = top.len();
+    }
+
+    /// Keeps the top `n` values of `stack`, for the context of an error
+    fn snapshot(&mut self, stack: &Stack, n: usize) {
+        let top = &stack[stack.len().saturating_sub(n)..];
+        self.context.stack.clear();
+        self.context.stack.extend(top.iter().map(StackValue::new));
+    }
+
+    /// Adds the call at `loc` to the trace of `err`, once for calls repeated
+    /// in a row
+    fn frame(
+        &mut self,
+        err: (RuntimeError, Loc),
+        kind: FrameKind,
+        loc: &Loc,
+    ) -> (RuntimeError, Loc) {
+        match self.context.trace.last_mut() {
+            Some(frame) if frame.kind == kind && frame.loc.start == loc.start => {
+                frame.repeated += 1
+            }
+            _ => self.context.trace.push(Frame {
+                kind,
+                loc: loc.clone(),
+                repeated: 1,
+            }),
+        }
+
+        err
+    }
+
+    /// The compiled body of a user function, or of its inverse, which is the
+    /// one given with `⁻¹f ← (…)` or else its body inverted.
+    fn function(
+        &mut self,
+        name: char,
+        inverse: bool,
+        loc: &Loc,
+    ) -> Result<Rc<Program>, (RuntimeError, Loc)> {
+        if let Some(program) = self.compiled.get(&(name, inverse)) {
+            return Ok(program.clone());
+        }
+
+        let body = match (self.functions.get(&name), inverse) {
+            (
+                Some(UserFunction {
+                    body: Some(body), ..
+                }),
+                false,
+            ) => body.clone(),
+            (
+                Some(UserFunction {
+                    inverse: Some(body),
+                    ..
+                }),
+                true,
+            ) => body.clone(),
+            (
+                Some(UserFunction {
+                    body: Some(body), ..
+                }),
+                true,
+            ) => Env::invert(&self.builtins, body)?,
+            _ => return Err((RuntimeError::FunctionNotFound(name), loc.clone())),
+        };
+
+        let program = Rc::new(compile(&body, &self.builtins)?);
+        self.compiled.insert((name, inverse), program.clone());
+        Ok(program)
+    }
+
+    /// Registers every definition in `tokens`, telling whether there were any
+    pub fn define(functions: &mut HashMap<char, UserFunction>, tokens: &[(Token, Loc)]) -> bool {
+        let mut defined = false;
+        for (token, _) in tokens {
+            if let Token::Definition(name, inverse, body) = token {
+                defined = true;
+                let function = functions.entry(*name).or_default();
+                if *inverse {
+                    function.inverse = Some(body.clone());
+                } else {
+                    function.body = Some(body.clone());
+                }
+            }
+        }
+
+        defined
+    }
+
+    /// Builds the inverse of a function body.
+    ///
+    /// The body is split into steps, each being a function and the literals
+    /// pushed right before it, like `+1` or `×2` in `(+1 ×2)`. The inverse
+    /// runs every step in reverse order, pushing the same literals before
+    /// calling the inverse of the function, so `(+1 ×2)` becomes `(⁻¹×2 ⁻¹+1)`.
+    pub(crate) fn invert(
+        builtins: &HashMap<char, Rc<CustomBuiltin>>,
+        body: &[(Token, Loc)],
+    ) -> Result<Vec<(Token, Loc)>, (RuntimeError, Loc)> {
+        let mut steps = Vec::<(Vec<(Token, Loc)>, (Token, Loc))>::new();
+        let mut literals = vec![];
+
+        // In the order they are ran
+        for (token, loc) in body.iter().rev() {
+            // Only a single value was flipped under the top of the stack
+            let flipped = literals.is_empty()
+                && matches!(steps.last(), Some((pushed, (Token::Flip, _))) if pushed.len() == 1);
+
+            let inverse = match token {
+                Token::Integer(_)
+                | Token::Rational(_)
+                | Token::Complex(_)
+                | Token::Infinity
+                | Token::Epsilon
+                | Token::Pi(_)
+                | Token::E(..)
+                | Token::List(_) => {
+                    literals.push((token.clone(), loc.clone()));
+                    continue;
+                }
+
+                // Their own inverses
+                Token::Flip | Token::Minus => token.clone(),
+                Token::Inverse(tok, _) => *tok.clone(),
+                Token::FunctionCall(c) if builtins.contains_key(c) => {
+                    if builtins[c].inverse.is_none() {
+                        return Err((RuntimeError::NoInverseOf(c.to_string()), loc.clone()));
+                    }
+                    Token::Inverse(Box::new(token.clone()), loc.clone())
                 }
-                Token::Inverse(tok, loc) => match tok.as_ref() {
-                    Token::FunctionCall(c) => match BUILTINS.get(&c) {
-                        Some(builtin) => match builtin.call_inverse(stack.clone()) {
-                            Err(err) => return Err((err, loc.clone())),
-                            Ok(stack) => {
-                                self.stack = stack;
-                            }
+                Token::FunctionCall(c) => match BUILTINS.get(c) {
+                    // `f↔x` gives `f` its right operand, so it is undone
+                    // knowing that one instead
+                    Some(
+                        builtin @ Builtin {
+                            signature: Signature { inputs: 2, .. },
+                            ..
                         },
-                        None => {
-                            return Err((RuntimeError::FunctionNotFound(c.clone()), loc.clone()))
+                    ) if flipped => {
+                        let (literals, _) = steps.pop().unwrap();
+                        if builtin.right_inverse().is_none() {
+                            let step = format!("{}↔{}", c, literals[0].0);
+                            return Err((RuntimeError::NoInverseOf(step), loc.clone()));
                         }
-                    },
-                    _ => Err((RuntimeError::InverseOfNonFunction, dbg!(loc.clone())))?,
+
+                        steps.push((literals, (Token::RightInverse(*c), loc.clone())));
+                        continue;
+                    }
+                    Some(builtin) if builtin.left_inverse().is_none() => {
+                        return Err((RuntimeError::NoInverseOf(c.to_string()), loc.clone()))
+                    }
+                    // User functions are looked up once ran, they may not be
+                    // defined yet
+                    _ => Token::Inverse(Box::new(token.clone()), loc.clone()),
                 },
-                Token::InvalidState => unreachable!(),
-            }
+                Token::Function(body) => Token::Function(Env::invert(builtins, body)?),
+                // Undoing `g` under `f` is doing the inverse of `g` under `f`
+                Token::Under(f, g) => Token::Under(
+                    f.clone(),
+                    Box::new((
+                        Token::Inverse(Box::new(g.0.clone()), g.1.clone()),
+                        g.1.clone(),
+                    )),
+                ),
 
-            if let Value::InvalidState(err) = self.stack.last().unwrap_or(&Value::Epsilon(0)) {
-                return Err((err.clone(), loc.clone()));
-            }
+                Token::Definition(..) => continue,
+                _ => return Err((RuntimeError::NoInverseOf(token.to_string()), loc.clone())),
+            };
+
+            steps.push((std::mem::take(&mut literals), (inverse, loc.clone())));
         }
 
-        Ok(())
+        // Pushing a value can't be undone
+        if let Some((token, loc)) = literals.first() {
+            return Err((RuntimeError::NoInverseOf(token.to_string()), loc.clone()));
+        }
+
+        // Back in source order, the last step to run being the first one
+        let mut inverse = vec![];
+        for (literals, function) in steps {
+            inverse.push(function);
+            inverse.extend(literals.into_iter().rev());
+        }
+
+        Ok(inverse)
     }
 }
 
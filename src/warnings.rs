@@ -0,0 +1,65 @@
+use crate::{err::Warning, value::Value};
+
+/// What a step turned `inputs` into, telling what `outputs` lost
+pub(crate) fn check(inputs: &[Value], outputs: &[Value]) -> Vec<Warning> {
+    let before = Seen::of(inputs);
+    let after = Seen::of(outputs);
+    let mut warnings = vec![];
+
+    if after.inexact && !before.inexact {
+        warnings.push(Warning::Inexact);
+    }
+    if after.undefined && !before.undefined {
+        let cancelled = (before.infinities != 0 && before.epsilons != 0)
+            || before.infinities == Seen::BOTH
+            || before.epsilons == Seen::BOTH;
+        warnings.push(match cancelled {
+            true => Warning::Cancellation,
+            false => Warning::Undefined,
+        });
+    }
+
+    warnings
+}
+
+/// What kinds of numbers some values hold, those of lists included
+#[derive(Default)]
+struct Seen {
+    inexact: bool,
+    undefined: bool,
+    /// `POSITIVE` and `NEGATIVE` bits, by sign
+    infinities: u8,
+    epsilons: u8,
+}
+
+impl Seen {
+    const POSITIVE: u8 = 1;
+    const NEGATIVE: u8 = 2;
+    const BOTH: u8 = Seen::POSITIVE | Seen::NEGATIVE;
+
+    fn of(values: &[Value]) -> Self {
+        let mut seen = Seen::default();
+        values.iter().for_each(|value| seen.walk(value));
+
+        seen
+    }
+
+    fn walk(&mut self, value: &Value) {
+        match value {
+            Value::Float(_) | Value::Complex(_) => self.inexact = true,
+            Value::Undefined => self.undefined = true,
+            Value::Infinity(sign) => self.infinities |= Seen::sign(*sign),
+            Value::Epsilon(sign) => self.epsilons |= Seen::sign(*sign),
+            Value::List(vals) | Value::Tuple(vals) => vals.iter().for_each(|val| self.walk(val)),
+            Value::Array(array) => array.data.iter().for_each(|val| self.walk(val)),
+            _ => {}
+        }
+    }
+
+    fn sign(sign: i8) -> u8 {
+        match sign {
+            ..0 => Seen::NEGATIVE,
+            _ => Seen::POSITIVE,
+        }
+    }
+}
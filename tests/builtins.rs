@@ -0,0 +1,253 @@
+//! Builtins on lists, at their edges.
+
+use kuhi::{
+    err::{ErrorKind, RuntimeError},
+    Interpreter,
+};
+
+/// What `src` leaves on top of the stack, as printed
+fn eval(src: &str) -> String {
+    let mut kuhi = Interpreter::new();
+    let stack = kuhi
+        .eval(src)
+        .unwrap_or_else(|errors| panic!("`{src}` failed: {errors:?}"));
+
+    stack.last().unwrap().to_string()
+}
+
+fn runtime_error(src: &str) -> RuntimeError {
+    match Interpreter::new().eval(src).unwrap_err().remove(0).kind {
+        ErrorKind::Runtime(err) => err,
+        kind => panic!("expected a runtime error, got {kind:?}"),
+    }
+}
+
+#[test]
+fn slices_count_from_either_end() {
+    assert_eq!(eval("⊘1‿3 ι5"), "[2 3]");
+    assert_eq!(eval("⊘1‿⁻1 ι5"), "[2 3 4]");
+    assert_eq!(eval("⊘⁻2‿5 ι5"), "[4 5]");
+    assert_eq!(eval("⊘⁻1‿5 ι5"), "[5]");
+    assert_eq!(eval("⊘⁻5‿⁻4 ι5"), "[1]");
+    assert_eq!(eval("⊘5‿5 ι5"), "[]");
+
+    assert!(matches!(
+        runtime_error("⊘3‿1 ι5"),
+        RuntimeError::InvalidSlice { start: 3, end: 1 }
+    ));
+    assert!(matches!(
+        runtime_error("⊘⁻1‿2 ι5"),
+        RuntimeError::InvalidSlice { start: 4, end: 2 }
+    ));
+    assert!(matches!(
+        runtime_error("⊘6‿6 ι5"),
+        RuntimeError::IndexOutOfRange { len: 5, .. }
+    ));
+}
+
+#[test]
+fn ragged_parts_are_tuples() {
+    assert_eq!(eval("⊟2 ι4"), "[[1 2] [3 4]]");
+    assert_eq!(eval("⊟2 ι5"), "[⟨1 2⟩ ⟨3 4⟩ ⟨5⟩]");
+    assert_eq!(eval("⊕0‿1‿1‿0 ι4"), "[[1 4] [2 3]]");
+    assert_eq!(eval("⊕0‿1‿0 ι3"), "[⟨1 3⟩ ⟨2⟩]");
+    assert_eq!(eval("⊜1‿1‿2‿2 ι4"), "[[1 2] [3 4]]");
+    assert_eq!(eval("⊜1‿1‿0‿1 ι4"), "[⟨1 2⟩ ⟨4⟩]");
+
+    // Later steps fail rather than panic
+    for src in [
+        "+ 1 ⊟2 ι5",
+        "⊥ ⊟2 ι3",
+        "⊡9 ⊟2 ι5",
+        "+ 1 ⊕0‿1‿0 ι3",
+        "+ 1 ⊜1‿1‿0‿1 ι4",
+    ] {
+        assert!(Interpreter::new().eval(src).is_err(), "`{src}` should fail");
+    }
+    assert_eq!(eval("△ ⊟2 ι5"), "[3]");
+}
+
+#[test]
+fn lists_of_mixed_shapes_are_errors() {
+    assert!(matches!(
+        runtime_error("∾ 1 ⊟1 ι2"),
+        RuntimeError::ListTypeMissmatch { .. }
+    ));
+    assert!(matches!(
+        runtime_error("∾ ⊟2 ι4 ⊟3 ι6"),
+        RuntimeError::ListElementSizeMissmatch {
+            first: 2,
+            second: 3
+        }
+    ));
+    assert!(matches!(
+        runtime_error("⋈ ι2 ⊟1 ι2"),
+        RuntimeError::ListTypeMissmatch { .. }
+    ));
+    assert!(matches!(
+        runtime_error("⊞ ι2 ⊟1 ι2"),
+        RuntimeError::ListTypeMissmatch { .. }
+    ));
+
+    assert_eq!(eval("∾ ⊟2 ι4 ⊟2 ι4"), "[[1 2] [3 4] [1 2] [3 4]]");
+    assert_eq!(eval("⋈ ι2 ι2"), "[[1 1] [2 2]]");
+    assert_eq!(eval("⊞ 1‿2 3‿4"), "[[[1 3] [1 4]] [[2 3] [2 4]]]");
+}
+
+#[test]
+fn undigits_fails_on_what_isnt_a_digit() {
+    assert_eq!(eval("⊥1‿2‿3"), "123");
+    assert_eq!(eval("⊥1‿12"), "22");
+
+    assert!(matches!(
+        runtime_error("⊥ ⊟2 ι3"),
+        RuntimeError::TypeMissmatch { .. }
+    ));
+}
+
+#[test]
+fn errors_in_elements_are_reported() {
+    assert!(matches!(
+        runtime_error("+ ι2 ⊟1 ι2"),
+        RuntimeError::TypeMissmatch { .. }
+    ));
+}
+
+#[test]
+fn reshaping_cycles_through_the_elements() {
+    assert_eq!(eval("↯2‿3 ι4"), "[[1 2 3] [4 1 2]]");
+    assert_eq!(eval("↯4 ↯2‿2 ι4"), "[1 2 3 4]");
+    assert_eq!(eval("↯0 ι3"), "[]");
+    assert_eq!(eval("△↯2‿3‿4 1"), "[2 3 4]");
+
+    assert!(matches!(
+        runtime_error("↯2 ⊘0‿0 ι3"),
+        RuntimeError::ShapeMissmatch { .. }
+    ));
+    // Too big to build at all, with or without limits
+    assert!(matches!(
+        runtime_error("↯4294967296‿4294967296 1"),
+        RuntimeError::ElementLimit(_)
+    ));
+    assert!(matches!(
+        runtime_error("↯4294967296‿4294967295 1"),
+        RuntimeError::ElementLimit(_)
+    ));
+}
+
+#[test]
+fn transposing_reverses_the_axes() {
+    assert_eq!(eval("⍉↯2‿3 ι6"), "[[1 4] [2 5] [3 6]]");
+    assert_eq!(eval("△⍉↯2‿1‿3 ι6"), "[3 1 2]");
+    assert_eq!(eval("♭⍉↯2‿1‿3 ι6"), "[1 4 2 5 3 6]");
+    assert_eq!(eval("⍉ι3"), "[1 2 3]");
+    assert_eq!(eval("⍉⍉↯2‿3 ι6"), "[[1 2 3] [4 5 6]]");
+}
+
+#[test]
+fn rank_counts_the_axes() {
+    assert_eq!(eval("⍤3"), "0");
+    assert_eq!(eval("⍤ι3"), "1");
+    assert_eq!(eval("⍤↯2‿3 ι6"), "2");
+    assert_eq!(eval("⍤↯2‿3‿4 1"), "3");
+}
+
+#[test]
+fn arithmetic_broadcasts_along_leading_axes() {
+    assert_eq!(eval("+ 1 ↯2‿2 ι4"), "[[2 3] [4 5]]");
+    assert_eq!(eval("+ ↯2‿3 ι6 10‿20"), "[[11 12 13] [24 25 26]]");
+    assert_eq!(eval("× ↯2‿2 ι4 ↯2‿2 ι4"), "[[1 4] [9 16]]");
+
+    assert!(matches!(
+        runtime_error("+ ↯2‿2 ι4 1‿2‿3"),
+        RuntimeError::ShapeMissmatch { .. }
+    ));
+    assert!(matches!(
+        runtime_error("+ ↯3‿2 ι6 ↯2‿3 ι6"),
+        RuntimeError::ShapeMissmatch { .. }
+    ));
+}
+
+#[test]
+fn logarithms_at_zero_and_below() {
+    assert_eq!(eval("ₙ2 8"), "3");
+    assert_eq!(eval("ₙ2 0"), "⁻∞");
+    assert_eq!(eval("ₙ2 0.0"), "⁻∞");
+    assert_eq!(eval("ₙ2 ε"), "⁻∞");
+    assert_eq!(eval("ₙ÷2 1 0"), "+∞");
+    assert_eq!(eval("ₙ2 ∞"), "+∞");
+    assert_eq!(eval("ₙ2 0‿4"), "[⁻∞ 2]");
+
+    // Not a single limit
+    assert_eq!(eval("ₙ⁻2 0"), "undef");
+    assert_eq!(eval("ₙ0 5"), "undef");
+    assert_eq!(eval("ₙ∞ 0"), "undef");
+    assert!(matches!(runtime_error("ₙ1 5"), RuntimeError::DivideByZero));
+
+    // Exact when a power of the base, complex otherwise
+    assert_eq!(eval("ₙ⁻2 4"), "2");
+    assert_eq!(eval("ₙ⁻2 ⁻8"), "3");
+    assert_eq!(eval("ₙ2 ⁻1"), "0i4.532360141827194");
+}
+
+#[test]
+fn sorting_is_stable_and_by_value() {
+    assert_eq!(eval("∧3‿1‿2"), "[1 2 3]");
+    assert_eq!(eval("∨3‿1‿2"), "[3 2 1]");
+    assert_eq!(eval("∧⊘0‿0 ι3"), "[]");
+    assert_eq!(eval("∧π‿3.15‿3.14‿3"), "[3 157/50 1π/1 63/20]");
+    assert_eq!(eval("∧ ∞‿0‿ε"), "[0 +ε +∞]");
+    assert_eq!(eval("∧ 1‿2i1‿2i⁻1‿2"), "[1 2i⁻1 2 2i1]");
+    // Rows of an array, as a whole
+    assert_eq!(eval("∧↯2‿2 4‿3‿2‿1"), "[[2 1] [4 3]]");
+
+    // Equal values keep their order
+    assert_eq!(eval("⍋3‿1‿2‿1"), "[1 3 2 0]");
+    assert_eq!(eval("⍒3‿1‿2‿1"), "[0 2 1 3]");
+    assert_eq!(eval("⍋↯2‿2 4‿3‿2‿1"), "[1 0]");
+    assert_eq!(eval("⊏⍋3‿1‿2 3‿1‿2"), "[1 2 3]");
+
+    for src in ["∧3", "⍋3", "∧ ⟨1 2⟩"] {
+        assert!(matches!(
+            runtime_error(src),
+            RuntimeError::TypeMissmatch { .. }
+        ));
+    }
+}
+
+#[test]
+fn searching_finds_the_first_place() {
+    assert_eq!(eval("⍸4 1‿3‿5‿7"), "2");
+    assert_eq!(eval("⍸3 1‿3‿3‿7"), "1");
+    assert_eq!(eval("⍸0 1‿3"), "0");
+    assert_eq!(eval("⍸9 1‿3"), "2");
+    assert_eq!(eval("⍸1 ⊘0‿0 ι3"), "0");
+
+    assert_eq!(eval("⊐3 5‿4‿3"), "2");
+    assert_eq!(eval("⊐3 5‿3‿3"), "1");
+    assert_eq!(eval("⊐9 5‿4‿3"), "3");
+    assert_eq!(eval("∊3 5‿4‿3"), "1");
+    assert_eq!(eval("∊9 5‿4‿3"), "0");
+
+    for src in ["⍸1 2", "⊐1 2"] {
+        assert!(matches!(
+            runtime_error(src),
+            RuntimeError::TypeMissmatch { .. }
+        ));
+    }
+}
+
+#[test]
+fn unique_and_count_agree() {
+    assert_eq!(eval("⍷3‿1‿3‿2‿1"), "[3 1 2]");
+    assert_eq!(eval("⊒3‿1‿3‿2‿1"), "[2 2 1]");
+    assert_eq!(eval("⍷ ÷2 1‿2‿2‿4"), "[1/2 1 2]");
+    assert_eq!(eval("⊒ ÷2 1‿2‿2‿4"), "[1 2 1]");
+    assert_eq!(eval("⍷⊘0‿0 ι3"), "[]");
+    assert_eq!(eval("⊒⊘0‿0 ι3"), "[]");
+
+    assert!(matches!(
+        runtime_error("⍷3"),
+        RuntimeError::TypeMissmatch { .. }
+    ));
+}
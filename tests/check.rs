@@ -0,0 +1,96 @@
+//! Finding stack underflows before anything is ran.
+
+use kuhi::{
+    check::check,
+    err::CheckError,
+    parser::{parse, Loc},
+    value::Value,
+    vm::Env,
+};
+use rug::Integer;
+
+/// Checks `src` with `stack` values already on the stack, giving back the
+/// step it underflows at, what it takes and what the stack would have
+fn underflow(src: &str, stack: usize) -> Option<(&str, usize, usize)> {
+    let mut loc = Loc {
+        start: 0,
+        end: 0,
+        line: 1,
+        column: 1,
+    };
+    let tokens = parse(src, &mut loc).unwrap_or_else(|err| panic!("`{src}`: {err:?}"));
+    let mut env = Env::new(vec![]);
+    env.stack = vec![Value::Integer(Integer::from(1)); stack];
+
+    match check(&tokens, &env) {
+        Ok(()) => None,
+        Err((CheckError::StackUnderflow { len, arity }, loc)) => {
+            // `end` is where the last char starts
+            let end = loc.end + src[loc.end..].chars().next().map_or(1, char::len_utf8);
+            Some((&src[loc.start..end], arity, len))
+        }
+    }
+}
+
+#[test]
+fn enough_values_are_accepted() {
+    for src in [
+        "+1 2",
+        "+ ×2 3 4",
+        ". ⇌ 1‿2",
+        "⟨+1 2⟩",
+        "⍜(×10)(+1) 2",
+        "f 2\nf ← (+1)",
+        "⁻¹(×2 +1) 8",
+    ] {
+        assert_eq!(underflow(src, 0), None, "`{src}`");
+    }
+
+    // Taken from what is already there
+    assert_eq!(underflow("+", 2), None);
+    assert_eq!(underflow("f\nf ← (× .)", 1), None);
+}
+
+#[test]
+fn underflows_are_rejected_where_they_happen() {
+    assert_eq!(underflow("+ ×2 3", 0), Some(("+", 2, 1)));
+    assert_eq!(underflow("+1", 0), Some(("+", 2, 1)));
+    assert_eq!(underflow("+", 1), Some(("+", 2, 1)));
+    // Inside brackets, at the step itself
+    assert_eq!(underflow("(+1)", 0), Some(("+", 2, 1)));
+    // Tuples are ran on an empty stack
+    assert_eq!(underflow("⟨+1⟩ 2", 0), Some(("+", 2, 1)));
+    // At the call, with what the function takes
+    assert_eq!(underflow("f 2\nf ← (+ +)", 0), Some(("f", 3, 1)));
+    assert_eq!(underflow("⍜(×10)(+) 2", 0), Some(("⍜(×10)(+)", 2, 1)));
+}
+
+#[test]
+fn inverses_take_what_was_given_back() {
+    // `×2` gives back one value, so its inverse takes one
+    assert_eq!(underflow("⁻¹(×2 +1)", 0), Some(("(×2 +1)", 1, 0)));
+    // Binary builtins are also given their known operand
+    assert_eq!(underflow("⁻¹+1", 0), Some(("+", 2, 1)));
+    // The inverse given, rather than the body swapped
+    assert_eq!(
+        underflow("⁻¹f\nf ← (×2)\n⁻¹f ← (÷2 +)", 1),
+        Some(("f", 2, 1))
+    );
+}
+
+#[test]
+fn unknown_sizes_are_left_for_the_runtime() {
+    for src in [
+        // Can't be inverted, the runtime says why
+        "⁻¹3",
+        "⁻¹(. +1) 3",
+        "⁻¹⊡1 2",
+        "+ ⁻¹(. +1)",
+        // Leaves as many values as the list has
+        "+ ⊔ 1‿2",
+        // Calls itself
+        "+ f 1\nf ← (f)",
+    ] {
+        assert_eq!(underflow(src, 0), None, "`{src}`");
+    }
+}
@@ -0,0 +1,98 @@
+//! Compiled programs give what they gave when their tokens were ran one by
+//! one, before `bytecode::compile`.
+
+use kuhi::{
+    parser::{parse, Loc, Token},
+    vm::Env,
+};
+
+fn start() -> Loc {
+    Loc {
+        start: 0,
+        end: 0,
+        line: 1,
+        column: 1,
+    }
+}
+
+/// The whole stack `tokens` leave, the top last
+fn run(tokens: Vec<(Token, Loc)>) -> String {
+    let mut env = Env::new(tokens);
+    env.run().unwrap();
+
+    env.stack
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn eval(src: &str) -> String {
+    run(parse(src, &mut start()).unwrap())
+}
+
+/// As given by running the tokens one by one
+const PROGRAMS: &[(&str, &str)] = &[
+    ("+1 ×2 3", "7"),
+    ("+ π 1", "4.141592653589793"),
+    ("÷3 1", "1/3"),
+    (". 3", "3 3"),
+    ("+ ι3 ι3", "[2 4 6]"),
+    // Inverses
+    ("⁻¹(×2 +1) 9", "7/2"),
+    ("⁻¹+2 10", "8"),
+    ("⁻¹÷4 3", "12"),
+    ("⁻¹ⁿ2 9", "3"),
+    ("⁻¹(⇌ +1) 1‿2‿3", "[2 1 0]"),
+    ("⁻¹⁻¹(×2) 4", "8"),
+    // Under
+    ("⍜(×10)(+1) 2", "21/10"),
+    ("⍜(+1)(×2) 3", "7"),
+    ("⍜⇌(+1) 1‿2‿3", "[2 3 4]"),
+    ("⍜(×2)(⁻¹×3) 6", "2"),
+    // Tuples
+    ("⟨1 +1 2⟩", "⟨1 3⟩"),
+    ("⟨⟨1⟩ 2⟩", "⟨⟨1⟩ 2⟩"),
+    ("⟨⟩", "⟨⟩"),
+];
+
+#[test]
+fn programs_give_what_they_gave() {
+    for (src, stack) in PROGRAMS {
+        assert_eq!(eval(src), *stack, "`{src}`");
+    }
+}
+
+#[test]
+fn functions_give_what_their_body_gives() {
+    for (src, stack) in PROGRAMS {
+        // Compiled on their own, when first called
+        assert_eq!(eval(&format!("f\nf ← ({src})")), *stack, "`{src}`");
+    }
+
+    assert_eq!(eval("g f 3\nf ← (×2 +1)\ng ← (f f)"), "38");
+    assert_eq!(eval("⟨f 1⟩\nf ← (. +1)"), "⟨2 2⟩");
+    // The last definition is the one called
+    assert_eq!(eval("f 5\nf ← (×2)\nf ← (+1)"), "6");
+    // Their body inverted, unless an inverse is given
+    assert_eq!(eval("⁻¹f 9\nf ← (×3 +1)"), "2");
+    assert_eq!(eval("⁻¹f 9\nf ← (×3 +1)\n⁻¹f ← (+1)"), "10");
+}
+
+#[test]
+fn scopes_run_on_the_values_of_a_list() {
+    // Not written in the source, but built by hosts
+    for (body, list, stack) in [
+        ("+", "1‿2", "3"),
+        ("+1", "ι3", "⟨4 2 1⟩"),
+        (".", "1‿2", "⟨2 2 1⟩"),
+    ] {
+        let mut tokens = parse(list, &mut start()).unwrap();
+        tokens.insert(
+            0,
+            (Token::Scope(parse(body, &mut start()).unwrap()), start()),
+        );
+
+        assert_eq!(run(tokens), stack, "`{body}` on `{list}`");
+    }
+}
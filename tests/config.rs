@@ -0,0 +1,179 @@
+//! Aliases from the user's config.
+
+use kuhi::{
+    config::{Config, Conflict},
+    formatter::Formatter,
+};
+
+#[test]
+fn aliases_are_read() {
+    let config = Config::parse(
+        r#"
+        [aliases]
+        thorn = "þ"
+        square = "pow 2"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.aliases,
+        [
+            ("square".to_owned(), "pow 2".to_owned()),
+            ("thorn".to_owned(), "þ".to_owned()),
+        ]
+    );
+    assert!(config.conflicts.is_empty());
+
+    // Nothing set
+    assert!(Config::parse("").unwrap().aliases.is_empty());
+    assert!(Config::parse("[other]\npi = \"p\"")
+        .unwrap()
+        .aliases
+        .is_empty());
+}
+
+#[test]
+fn conflicts_are_left_out() {
+    let config = Config::parse(
+        r#"
+        [aliases]
+        sqrt = "∛"
+        "a b" = "1"
+        "√√" = "1"
+        n = 3
+        ok = "ω"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.aliases, [("ok".to_owned(), "ω".to_owned())]);
+    assert_eq!(
+        config.conflicts,
+        [
+            Conflict::InvalidName("a b".to_owned()),
+            Conflict::NotAString("n".to_owned()),
+            Conflict::Builtin {
+                name: "sqrt".to_owned(),
+                glyph: "√".to_owned()
+            },
+            Conflict::InvalidName("√√".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn names_taking_over_glyphs_are_left_out() {
+    let config = Config::parse(
+        r#"
+        [aliases]
+        "-" = "⊢"
+        "+" = "⊢"
+        "1" = "⊢"
+        "x2" = "⊢"
+        "a.b" = "⊢"
+        ":)" = "⊢"
+        "=>" = "⊢"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.aliases, [("=>".to_owned(), "⊢".to_owned())]);
+    let taken = config
+        .conflicts
+        .iter()
+        .map(|conflict| match conflict {
+            Conflict::Taken { name, glyph } => (name.as_str(), *glyph),
+            conflict => panic!("expected `Taken`, got {conflict:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        taken,
+        [
+            ("+", '+'),
+            ("-", '-'),
+            ("1", '1'),
+            (":)", ':'),
+            ("a.b", '.'),
+            ("x2", '2'),
+        ]
+    );
+}
+
+#[test]
+fn aliases_used_by_snippets_are_left_out() {
+    let config = Config::parse(
+        r#"
+        [aliases]
+        inc = "inc 1"
+        a = "b"
+        b = "c"
+        yes = "no"
+        no = "yes"
+        square = "pow 2"
+        sq = "sqrt square"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.aliases,
+        [
+            ("b".to_owned(), "c".to_owned()),
+            ("square".to_owned(), "pow 2".to_owned()),
+        ]
+    );
+    assert_eq!(
+        config.conflicts,
+        [
+            Conflict::Chained {
+                name: "a".to_owned(),
+                other: "b".to_owned()
+            },
+            Conflict::Recursive("inc".to_owned()),
+            Conflict::Chained {
+                name: "no".to_owned(),
+                other: "yes".to_owned()
+            },
+            Conflict::Chained {
+                name: "sq".to_owned(),
+                other: "square".to_owned()
+            },
+            Conflict::Chained {
+                name: "yes".to_owned(),
+                other: "no".to_owned()
+            },
+        ]
+    );
+
+    // What is left formats once and for all
+    let formatter = |src: &str| Formatter::new(src.to_owned()).with_snippets(&config.aliases);
+    let once = formatter("inc a b square 3").format();
+    assert_eq!(once, "inc a c ⁿ 2 3");
+    assert_eq!(formatter(&once).format(), once);
+}
+
+#[test]
+fn invalid_configs_are_errors() {
+    assert!(Config::parse("[aliases").is_err());
+    assert!(Config::read("does/not/exist.toml".as_ref()).is_err());
+}
+
+#[test]
+fn snippets_are_formatted() {
+    let aliases = [
+        ("square".to_owned(), "pow 2".to_owned()),
+        ("thorn".to_owned(), "þ".to_owned()),
+        ("twice".to_owned(), "⍜(×2)".to_owned()),
+    ];
+    let formatter = |src: &str| Formatter::new(src.to_owned()).with_snippets(&aliases);
+
+    assert_eq!(formatter("square thorn 3").format(), "ⁿ 2 þ 3");
+    assert_eq!(
+        formatter("twice(+1) 3 # square").format(),
+        "⍜(×2)(+1) 3 # square"
+    );
+
+    // Glyphs get their names back, snippets are kept
+    assert_eq!(formatter("þ ⁿ 2").unformat(), "thorn pow 2");
+}
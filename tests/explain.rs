@@ -0,0 +1,28 @@
+//! Error codes and their explanations.
+
+use kuhi::{explain::explain, Interpreter};
+
+#[test]
+fn every_code_is_explained() {
+    for n in 1..=35 {
+        let code = format!("K{n:04}");
+        let explanation = explain(&code).unwrap_or_else(|| panic!("{code} has no explanation"));
+
+        assert!(explanation.starts_with(&format!("{code}: ")));
+    }
+
+    assert!(explain("K0036").is_none());
+    assert!(explain("k0001").is_none());
+}
+
+#[test]
+fn diagnostics_have_explained_codes() {
+    let mut kuhi = Interpreter::new();
+
+    for (src, code) in [("⁻¹", "K0005"), ("ι ⟨1⟩", "K0017"), ("+1 +1", "K0030")] {
+        let err = kuhi.eval(src).unwrap_err().remove(0);
+
+        assert_eq!(err.code(), code);
+        assert!(explain(err.code()).is_some());
+    }
+}
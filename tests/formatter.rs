@@ -0,0 +1,146 @@
+//! Turning ASCII spellings into glyphs, and back.
+
+use kuhi::{
+    formatter::{Formatter, SYMBOLS},
+    parser::{parse, Loc},
+};
+
+fn format(src: &str) -> String {
+    Formatter::new(src.to_owned()).format()
+}
+
+#[test]
+fn longest_names_win() {
+    assert_eq!(format("sinh 1"), "ⓔ 1");
+    assert_eq!(format("sins 1"), "Ⓞ 1");
+    assert_eq!(format("sin 1"), "◯ 1");
+    assert_eq!(format("epsilon"), "ε");
+    assert_eq!(format("undigits digits"), "⊥ ⊤");
+    assert_eq!(format("x <- (sqrt pi)"), "x ← (√ π)");
+    assert_eq!(format("1_2_3"), "1‿2‿3");
+}
+
+#[test]
+fn comments_and_strings_are_kept() {
+    assert_eq!(format("sqrt 4 # sqrt pi"), "√ 4 # sqrt pi");
+    assert_eq!(format("#{ sqrt #{ pi }# }# pi"), "#{ sqrt #{ pi }# }# π");
+    assert_eq!(format("pi\n# pi\npi"), "π\n# pi\nπ");
+    assert_eq!(format("* \"a * b \\\" pi\" *"), "× \"a * b \\\" pi\" ×");
+
+    // Left for the parser to report
+    assert_eq!(format("pi #{ pi"), "π #{ pi");
+    assert_eq!(format("pi \"pi"), "π \"pi");
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let mut sources = vec![
+        "sinh sins sin epsilon pi tau".to_owned(),
+        "f <- (inverse pow 2 *3 % 4) `1 : flip swap".to_owned(),
+        "reshape 2_2 iota 4 # iota".to_owned(),
+    ];
+    // Every name, and every name right before another
+    for (name, _) in SYMBOLS {
+        sources.push(name.to_string());
+        for (next, _) in SYMBOLS {
+            sources.push(format!("{name}{next}"));
+        }
+    }
+
+    for src in sources {
+        let once = format(&src);
+        assert_eq!(format(&once), once, "formatting `{src}` twice");
+    }
+}
+
+#[test]
+fn aliases_replace_names() {
+    let formatted = Formatter::new("double pi dbl".to_owned())
+        .with_aliases(vec![
+            ("double".to_owned(), 'ð'),
+            ("pi".to_owned(), 'þ'),
+            // Could be read as part of another name
+            ("dbl".to_owned(), 'd'),
+        ])
+        .format();
+
+    assert_eq!(formatted, "ð þ dbl");
+}
+
+fn unformat(src: &str) -> String {
+    Formatter::new(src.to_owned()).unformat()
+}
+
+/// The tokens `src` parses to, spacing left out
+fn tokens(src: &str) -> Vec<String> {
+    let mut loc = Loc {
+        start: 0,
+        end: 0,
+        line: 1,
+        column: 1,
+    };
+    parse(src, &mut loc)
+        .unwrap_or_else(|errors| panic!("`{src}` doesn't parse: {errors:?}"))
+        .into_iter()
+        .map(|(token, _)| token.to_string())
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+#[test]
+fn unformatting_gives_ascii_names() {
+    assert_eq!(unformat("√ π"), "sqrt pi");
+    assert_eq!(unformat("↕ ⍷ ♭"), "flip dedup ravel");
+    assert_eq!(unformat("f ← (⁻¹ⁿ2) ⟨1 ¯⟩"), "f <- (inverse pow 2) {1 ¯}");
+    assert_eq!(unformat("◯ⓔ π‿τ"), "sin sinh pi_tau");
+    assert_eq!(unformat("√ # √ \"√\""), "sqrt # √ \"√\"");
+
+    // Spaced out from words and numbers, but not from symbols
+    assert_eq!(unformat("◯ι3"), "sin iota 3");
+    assert_eq!(unformat("⊤⊥"), "digits undigits");
+    assert_eq!(unformat("⁻¹f 2"), "inverse f 2");
+    assert_eq!(unformat("×2 ÷3 ⁻1‿2"), "*2 %3 `1_2");
+
+    // Names already there are kept
+    assert_eq!(unformat("sqrt √"), "sqrt sqrt");
+    let once = unformat("◯ⓔ ⍜(×10)(+1) 2");
+    assert_eq!(unformat(&once), once);
+}
+
+#[test]
+fn unformatting_round_trips() {
+    let mut sources = vec![
+        "f ← (+1 ×2)\n⁻¹f ← (÷2 -1)\nf 3".to_owned(),
+        "⍜(×10)(+1) 2 # under".to_owned(),
+        "↯2‿2 ι4 ⟨1 2⟩ ↺ 1 2 3".to_owned(),
+        "+ π 1 × ∞ ε 2i ⁻3 #{ pi }#".to_owned(),
+    ];
+    // Every glyph, and every glyph right before another, or a letter
+    let glyphs = SYMBOLS
+        .iter()
+        .map(|(_, glyph)| *glyph)
+        .filter(|glyph| !["‿", "⟨", "⟩", "←", "⁻", "⁻¹", "⍜"].contains(glyph))
+        .collect::<Vec<_>>();
+    for glyph in &glyphs {
+        sources.push(format!("{glyph} 1"));
+        sources.push(format!("f{glyph}h 1"));
+        for next in &glyphs {
+            sources.push(format!("{glyph}{next} 1"));
+        }
+    }
+
+    for src in sources {
+        let unformatted = unformat(&src);
+        assert!(
+            unformatted.is_ascii(),
+            "`{src}` unformats to `{unformatted}`"
+        );
+        assert_eq!(
+            tokens(&format(&unformatted)),
+            tokens(&src),
+            "`{src}` round trip"
+        );
+        // The spaces it added are kept
+        assert_eq!(unformat(&format(&unformatted)), unformatted);
+    }
+}
@@ -0,0 +1,336 @@
+//! The embedding API, as used from outside the crate.
+
+use std::time::{Duration, Instant};
+
+use kuhi::{
+    builtins::{CustomBuiltin, Signature},
+    err::{ErrorKind, FrameKind, RuntimeError},
+    value::Value,
+    Interpreter,
+};
+use rug::{Integer, Rational};
+
+#[test]
+fn eval_keeps_the_stack_between_calls() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("1 2").unwrap();
+    let stack = kuhi.eval("+").unwrap();
+
+    assert_eq!(stack, vec![Value::Integer(Integer::from(3))]);
+}
+
+#[test]
+fn definitions_are_kept_between_calls() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("f ← (×2)").unwrap();
+    kuhi.eval("f 21").unwrap();
+
+    assert_eq!(kuhi.pop_as::<i64>().unwrap(), 42);
+}
+
+#[test]
+fn values_convert_to_rust_numbers() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("÷3 1").unwrap();
+    assert_eq!(kuhi.pop_as::<Rational>().unwrap(), Rational::from((1, 3)));
+
+    kuhi.push(Rational::from((4, 2)));
+    assert_eq!(kuhi.pop_as::<Integer>().unwrap(), 2);
+
+    kuhi.eval("π").unwrap();
+    assert!((kuhi.pop_as::<f64>().unwrap() - std::f64::consts::PI).abs() < 1e-15);
+}
+
+#[test]
+fn failed_conversions_leave_the_stack() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("÷3 1").unwrap();
+
+    assert!(matches!(
+        kuhi.pop_as::<i64>(),
+        Err(RuntimeError::TypeMissmatch { .. })
+    ));
+    assert_eq!(kuhi.stack().len(), 1);
+}
+
+#[test]
+fn errors_point_into_the_source() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("1 2").unwrap();
+    let err = kuhi.eval("⊡5 ⟨1 2⟩").unwrap_err().remove(0);
+
+    assert!(matches!(err.kind, ErrorKind::Runtime(_)));
+    assert_eq!(err.loc.line, 2);
+    assert_eq!(&kuhi.source()[err.span.clone()], "⊡");
+}
+
+#[test]
+fn errors_leave_the_stack_as_it_was() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("1 2").unwrap();
+    assert!(kuhi.eval("⊡5 ⟨1 2⟩ 3").is_err());
+    assert!(matches!(
+        kuhi.eval("1 )").unwrap_err().remove(0).kind,
+        ErrorKind::Syntax(_)
+    ));
+
+    // Still lines up after a syntax error
+    let err = kuhi.eval("+ + +").unwrap_err().remove(0);
+    assert!(matches!(err.kind, ErrorKind::Check(_)));
+    assert_eq!(err.loc.line, 4);
+    assert_eq!(&kuhi.source()[err.span.clone()], "+");
+
+    assert_eq!(kuhi.stack().len(), 2);
+}
+
+#[test]
+fn errors_leave_the_definitions_as_they_were() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("f ← (+1)").unwrap();
+    kuhi.eval("f 1").unwrap();
+    assert!(kuhi.eval("f ← (+100)\n⊡5 ⟨⟩ f 1").is_err());
+
+    kuhi.eval("f 1").unwrap();
+    assert_eq!(kuhi.pop_as::<i64>().unwrap(), 2);
+}
+
+#[test]
+fn inverses_are_checked_only_when_they_exist() {
+    let mut kuhi = Interpreter::new();
+
+    // Left for the runtime, which knows why they can't be inverted
+    for (src, code) in [
+        ("⁻¹3", "K0025"),
+        ("⁻¹(. +1) 3", "K0024"),
+        ("⁻¹⊡1 2", "K0024"),
+    ] {
+        assert_eq!(
+            kuhi.eval(src).unwrap_err().remove(0).code(),
+            code,
+            "`{src}`"
+        );
+    }
+
+    assert_eq!(
+        kuhi.eval("⁻¹(×2 +1)").unwrap_err().remove(0).code(),
+        "K0030"
+    );
+    assert_eq!(
+        kuhi.eval("⁻¹⍜(×10)(+1)").unwrap_err().remove(0).code(),
+        "K0030"
+    );
+    kuhi.eval("⁻¹(×2 +1) 8").unwrap();
+    assert_eq!(kuhi.pop_as::<i64>().unwrap(), 3);
+}
+
+#[test]
+fn every_syntax_error_is_reported() {
+    let mut kuhi = Interpreter::new();
+
+    let errors = kuhi.eval("1 )\n(2\n⟩ 3 ⟩\n(+ ])").unwrap_err();
+    let found = errors
+        .iter()
+        .map(|err| (err.code(), err.loc.line, &kuhi.source()[err.span.clone()]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            ("K0002", 1, ")"),
+            ("K0002", 2, "("),
+            ("K0004", 3, "⟩"),
+            ("K0004", 3, "⟩"),
+            ("K0003", 4, "]"),
+        ]
+    );
+
+    // Still lines up after them
+    let err = kuhi.eval("+ +").unwrap_err().remove(0);
+    assert_eq!(err.loc.line, 5);
+    assert_eq!(&kuhi.source()[err.span.clone()], "+");
+}
+
+#[test]
+fn errors_deep_in_brackets_are_found_once() {
+    let mut kuhi = Interpreter::new();
+    let src = format!("{}]{}", "(".repeat(100), ")".repeat(100));
+
+    // Each level used to be parsed twice, in time exponential in the depth
+    let start = Instant::now();
+    let errors = kuhi.eval(&src).unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code(), "K0003");
+    assert_eq!(errors[0].loc.column, 101);
+}
+
+#[test]
+fn errors_in_functions_have_a_trace() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("g ← (ι)\nf ← (+1 g)").unwrap();
+    let err = kuhi.eval("⟨f 0⟩").unwrap_err().remove(0);
+
+    assert_eq!(&kuhi.source()[err.span.clone()], "ι");
+    let trace = err
+        .trace
+        .iter()
+        .map(|(frame, span)| (frame.kind, &kuhi.source()[span.clone()]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        trace,
+        vec![
+            (FrameKind::Function('g'), "g"),
+            (FrameKind::Function('f'), "f"),
+            (FrameKind::Tuple, "⟨f 0⟩"),
+        ]
+    );
+}
+
+#[test]
+fn errors_show_the_stack_before_the_step() {
+    let mut kuhi = Interpreter::new();
+
+    let err = kuhi.eval("+ ⟨1 2⟩ ι20 3").unwrap_err().remove(0);
+    let stack = err
+        .stack
+        .iter()
+        .map(|value| (value.to_string(), value.types()))
+        .collect::<Vec<_>>();
+
+    // Only what `+` was given, the top last
+    assert_eq!(
+        stack,
+        vec![
+            (
+                "[1 2 3 4 5 6 7 8], the first 8 of 20".to_string(),
+                vec![
+                    "List".to_string(),
+                    "Number".to_string(),
+                    "Integer".to_string()
+                ]
+            ),
+            ("⟨1 2⟩".to_string(), vec!["Tuple".to_string()]),
+        ]
+    );
+}
+
+#[test]
+fn errors_cut_every_axis_of_the_stack() {
+    let mut kuhi = Interpreter::new();
+
+    let err = kuhi.eval("+ ⟨1⟩ ↯3‿20 ι60").unwrap_err().remove(0);
+    assert_eq!(
+        err.stack[0].to_string(),
+        "[[1 2 3 4 5 6 7 8] [21 22 23 24 25 26 27 28] [41 42 43 44 45 46 47 48]]"
+    );
+
+    // Nothing kept from the steps before
+    let err = kuhi.eval("ι ⟨⟩ +1 ↯2‿20 ι40").unwrap_err().remove(0);
+    let stack = err
+        .stack
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(stack, vec!["⟨⟩".to_string()]);
+}
+
+#[test]
+fn errors_convert_to_json() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("1").unwrap();
+    let json = kuhi.eval("ι ⟨⟩").unwrap_err().remove(0).to_json();
+
+    assert_eq!(json["kind"], "runtime");
+    assert_eq!(json["code"], "K0017");
+    assert_eq!(json["message"], "expected type `Integer`, got `Tuple`");
+    assert_eq!(json["span"]["start"], 2);
+    assert_eq!(json["span"]["end"], 4);
+    assert_eq!(json["line"], 2);
+    assert_eq!(json["column"], 1);
+    assert_eq!(json["stack"][0]["types"][0], "Tuple");
+
+    let json = kuhi.eval("1 )").unwrap_err().remove(0).to_json();
+    assert_eq!(json["kind"], "syntax");
+    assert_eq!(json["code"], "K0002");
+    assert_eq!(json["line"], 3);
+}
+
+#[test]
+fn unknown_functions_suggest_builtins() {
+    let mut kuhi = Interpreter::new();
+
+    // Not formatted, so `sqrt` is 4 unknown functions
+    let err = kuhi.eval("sqrt 4").unwrap_err().remove(0);
+    assert_eq!(err.help.as_deref(), Some("did you mean `sqrt` → `√`?"));
+
+    let err = kuhi.eval("revrse 1‿2").unwrap_err().remove(0);
+    assert_eq!(err.help.as_deref(), Some("did you mean `reverse` → `⇌`?"));
+
+    let err = kuhi.eval("v 1").unwrap_err().remove(0);
+    assert_eq!(err.help.as_deref(), Some("did you mean `∨` or `▽`?"));
+    assert_eq!(err.to_json()["help"], "did you mean `∨` or `▽`?");
+
+    let err = kuhi.eval("f 1").unwrap_err().remove(0);
+    assert_eq!(err.help, None);
+}
+
+#[test]
+fn registered_builtins_can_be_called() {
+    let mut kuhi = Interpreter::new();
+    kuhi.register(
+        'ð',
+        CustomBuiltin::new(
+            |stack| {
+                let x = stack.pop().unwrap();
+                stack.push(x.clone() + x);
+                Ok(())
+            },
+            Signature::new(1, 1),
+        ),
+    );
+
+    kuhi.eval("ð ð 3").unwrap();
+
+    assert_eq!(kuhi.pop_as::<i64>().unwrap(), 12);
+}
+
+#[test]
+fn registered_inverses_check_the_stack_first() {
+    // Would panic on a stack too short
+    let double = CustomBuiltin::new(
+        |stack| {
+            let x = stack.pop().unwrap();
+            stack.push(x.clone() + x);
+            Ok(())
+        },
+        Signature::new(1, 1),
+    )
+    .with_inverse(|stack| {
+        let x = stack.pop().unwrap();
+        stack.push(x * Value::from(Rational::from((1, 2))));
+        Ok(())
+    });
+
+    assert!(matches!(
+        double.call(&mut vec![]),
+        Err(RuntimeError::InvalidPop { len: 0, arity: 1 })
+    ));
+    assert!(matches!(
+        double.call_inverse(&mut vec![]),
+        Err(RuntimeError::InvalidPop { len: 0, arity: 1 })
+    ));
+
+    let mut kuhi = Interpreter::new();
+    kuhi.register('ð', double);
+    kuhi.eval("⁻¹ð 6").unwrap();
+    assert_eq!(kuhi.pop_as::<i64>().unwrap(), 3);
+}
@@ -0,0 +1,170 @@
+//! `f⁻¹(f(x)) == x` for every builtin that has an inverse.
+
+use kuhi::{
+    builtins::{Inverse, BUILTINS},
+    value::Value,
+};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    strategy::BoxedStrategy,
+    test_runner::{Config, TestCaseError, TestRunner},
+};
+use rug::{ops::Pow, Integer, Rational};
+
+fn integer(range: std::ops::Range<i64>) -> BoxedStrategy<Value> {
+    range.prop_map(|n| Value::Integer(Integer::from(n))).boxed()
+}
+
+fn rational() -> BoxedStrategy<Value> {
+    (-1000i64..1000, 1i64..100)
+        .prop_map(|(n, d)| Value::Rational(Rational::from((n, d))))
+        .boxed()
+}
+
+fn number() -> BoxedStrategy<Value> {
+    prop_oneof![integer(-1000..1000), rational()].boxed()
+}
+
+fn non_zero() -> BoxedStrategy<Value> {
+    number().prop_filter("non zero", |n| !n.is_zero()).boxed()
+}
+
+fn list() -> BoxedStrategy<Value> {
+    vec(number(), 0..8).prop_map(Value::List).boxed()
+}
+
+fn matrix() -> BoxedStrategy<Value> {
+    (1usize..4, 1usize..4)
+        .prop_flat_map(|(rows, cols)| vec(vec(number(), cols), rows))
+        .prop_map(|rows| Value::List(rows.into_iter().map(Value::List).collect()))
+        .boxed()
+}
+
+/// Stacks a builtin can be undone on, the top of the stack last. Binary
+/// builtins get `[x, y]` for `f y x`, and must be undoable knowing either.
+fn domain(c: char) -> BoxedStrategy<Vec<Value>> {
+    match c {
+        '+' | '-' => (number(), number()).prop_map(|(x, y)| vec![x, y]).boxed(),
+        '×' | '÷' => (non_zero(), non_zero())
+            .prop_map(|(x, y)| vec![x, y])
+            .boxed(),
+        // Bases above 1, so logarithms are unique
+        'ⁿ' => (integer(2..50), integer(1..6))
+            .prop_map(|(x, y)| vec![x, y])
+            .boxed(),
+        // Perfect powers, so roots stay exact
+        '√' => (2u32..20, 1u32..5)
+            .prop_map(|(b, y)| {
+                let x = Integer::from(b).pow(y);
+                vec![Value::Integer(x), Value::Integer(Integer::from(y))]
+            })
+            .boxed(),
+        'ₙ' => (2i64..10, (-4i32..5).prop_filter("non zero", |k| *k != 0))
+            .prop_map(|(y, k)| {
+                let x = Rational::from(y).pow(k);
+                vec![Value::Rational(x), Value::Integer(Integer::from(y))]
+            })
+            .boxed(),
+        '↻' => (list(), integer(-10..10))
+            .prop_map(|(x, y)| vec![x, y])
+            .boxed(),
+
+        '↕' => vec(number(), 2).boxed(),
+        '↺' => vec(number(), 3).boxed(),
+        // Principal values of `asin`
+        '◯' => (-15i64..=15)
+            .prop_map(|n| vec![Value::Rational(Rational::from((n, 10)))])
+            .boxed(),
+        'ⓔ' => rational().prop_map(|x| vec![x]).boxed(),
+        '⊤' => (0u64..1_000_000)
+            .prop_map(|n| vec![Value::Integer(Integer::from(n))])
+            .boxed(),
+        // No leading zeros, they are lost
+        '⊥' => (1u64..1_000_000)
+            .prop_map(|n| {
+                let digits = n
+                    .to_string()
+                    .chars()
+                    .map(|d| Value::Integer(Integer::from(d.to_digit(10).unwrap())))
+                    .collect();
+                vec![Value::List(digits)]
+            })
+            .boxed(),
+        '⇌' => list().prop_map(|x| vec![x]).boxed(),
+        '⍉' => matrix().prop_map(|x| vec![x]).boxed(),
+
+        c => panic!("`{c}` has an inverse, but no values to check it with"),
+    }
+}
+
+/// Equal, or as close as floats get.
+fn same(a: &Value, b: &Value) -> bool {
+    if a == b {
+        return true;
+    }
+    match a.clone() + -b.clone() {
+        Value::Float(diff) => diff.abs() < 1e-30,
+        _ => false,
+    }
+}
+
+fn check(c: char, undo: impl Fn(&[Value]) -> Result<(), String>) {
+    let mut runner = TestRunner::new(Config {
+        cases: 64,
+        // Failing cases are kept in `proptest-regressions`, like `proptest!` does
+        source_file: Some(file!()),
+        ..Config::default()
+    });
+
+    let result = runner.run(&domain(c), |stack| {
+        undo(&stack).map_err(TestCaseError::fail)
+    });
+    if let Err(err) = result {
+        panic!("`{c}` isn't undone: {err}");
+    }
+}
+
+#[test]
+fn inverses_undo_their_builtin() {
+    for (c, builtin) in BUILTINS.iter() {
+        match builtin.inverse {
+            Inverse::None => {}
+            Inverse::Whole(_) => check(*c, |stack| {
+                let mut done = stack.to_vec();
+                builtin.call(&mut done).map_err(|err| err.to_string())?;
+                let mut undone = done.clone();
+                builtin
+                    .call_inverse(&mut undone)
+                    .map_err(|err| err.to_string())?;
+
+                match undone.len() == stack.len()
+                    && undone.iter().zip(stack).all(|(a, b)| same(a, b))
+                {
+                    true => Ok(()),
+                    false => Err(format!("got {undone:?} back from {done:?}")),
+                }
+            }),
+            Inverse::Binary(left, right) => check(*c, |stack| {
+                let [x, y] = stack else { unreachable!() };
+                let mut done = stack.to_vec();
+                builtin.call(&mut done).map_err(|err| err.to_string())?;
+                let result = done.last().unwrap().clone();
+
+                // Each inverse is given the result, with the known operand on top
+                for (inverse, known, unknown) in [(left, y, x), (right, x, y)] {
+                    if inverse.is_none() {
+                        continue;
+                    }
+                    let mut undone = vec![result.clone(), known.clone()];
+                    inverse.unwrap()(&mut undone).map_err(|err| err.to_string())?;
+
+                    if !same(undone.last().unwrap(), unknown) {
+                        return Err(format!("got {:?} back from {result:?}", undone.last()));
+                    }
+                }
+                Ok(())
+            }),
+        }
+    }
+}
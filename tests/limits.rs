@@ -0,0 +1,122 @@
+//! Stopping programs that run too long or build values too big.
+
+use std::sync::atomic::Ordering;
+
+use kuhi::{
+    err::{ErrorKind, RuntimeError},
+    limits::{self, Limits},
+    Interpreter,
+};
+
+fn runtime_error(kuhi: &mut Interpreter, src: &str) -> RuntimeError {
+    match kuhi.eval(src).unwrap_err().remove(0).kind {
+        ErrorKind::Runtime(err) => err,
+        kind => panic!("expected a runtime error, got {kind:?}"),
+    }
+}
+
+#[test]
+fn step_limit_stops_long_programs() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_limits(Limits {
+        steps: Some(100),
+        ..Limits::default()
+    });
+
+    let err = runtime_error(&mut kuhi, &format!("{}0", "+1 ".repeat(60)));
+    assert!(matches!(err, RuntimeError::StepLimit(100)));
+    kuhi.eval(&format!("{}0", "+1 ".repeat(40))).unwrap();
+    // The count starts over for each eval
+    kuhi.eval(&"+1 ".repeat(40)).unwrap();
+
+    let err = runtime_error(&mut kuhi, "f ← (+1 f)\nf 0");
+    assert!(matches!(err, RuntimeError::StepLimit(100)));
+}
+
+#[test]
+fn element_limit_is_checked_before_building() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_limits(Limits {
+        elements: Some(1000),
+        ..Limits::default()
+    });
+
+    kuhi.eval("ι1000").unwrap();
+    let err = runtime_error(&mut kuhi, "ι1000000000000");
+    assert!(matches!(err, RuntimeError::ElementLimit(1000)));
+    // Counting the elements of nested lists
+    let err = runtime_error(&mut kuhi, "⊞ι100 ι100");
+    assert!(matches!(err, RuntimeError::ElementLimit(1000)));
+
+    // Nothing changed
+    assert_eq!(kuhi.stack().len(), 1);
+}
+
+#[test]
+fn bit_limit_stops_big_integers() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_limits(Limits {
+        bits: Some(64),
+        ..Limits::default()
+    });
+
+    kuhi.eval("ⁿ60 2").unwrap();
+    let err = runtime_error(&mut kuhi, "ⁿ1000000000 3");
+    assert!(matches!(err, RuntimeError::BitLimit(64)));
+    let err = runtime_error(&mut kuhi, "ⁿ⁻1000 3");
+    assert!(matches!(err, RuntimeError::BitLimit(64)));
+    let err = runtime_error(&mut kuhi, "×. ×. ×. 12345");
+    assert!(matches!(err, RuntimeError::BitLimit(64)));
+}
+
+#[test]
+fn depth_limit_stops_deep_calls() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_limits(Limits {
+        depth: Some(100),
+        ..Limits::default()
+    });
+
+    let err = runtime_error(&mut kuhi, "f 0\nf ← (f +1)");
+    assert!(matches!(err, RuntimeError::DepthLimit(100)));
+    let err = runtime_error(&mut kuhi, "g 0\ng ← (⟨g⟩)");
+    assert!(matches!(err, RuntimeError::DepthLimit(100)));
+
+    // Calls one after the other don't nest
+    kuhi.eval(&format!("{}0\nh ← (+1)", "h ".repeat(200)))
+        .unwrap();
+    assert_eq!(kuhi.pop_as::<i64>().unwrap(), 200);
+}
+
+#[test]
+fn deep_calls_are_stopped_by_default() {
+    // Tests run on spawned threads, with their smaller stack
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("h ← (h)").unwrap();
+    let err = runtime_error(&mut kuhi, "h 1");
+    assert!(matches!(err, RuntimeError::DepthLimit(limits::DEPTH)));
+    let err = runtime_error(&mut kuhi, "⁻¹k 1\nk ← (+1)\n⁻¹k ← (⍜(⁻¹k)(+1))");
+    assert!(matches!(err, RuntimeError::DepthLimit(limits::DEPTH)));
+
+    // Still there when setting the others
+    kuhi.set_limits(Limits {
+        steps: Some(1_000_000),
+        ..Limits::default()
+    });
+    let err = runtime_error(&mut kuhi, "h 1");
+    assert!(matches!(err, RuntimeError::DepthLimit(limits::DEPTH)));
+}
+
+#[test]
+fn cancelling_stops_the_program() {
+    let mut kuhi = Interpreter::new();
+    let cancel = kuhi.canceller();
+
+    cancel.store(true, Ordering::Relaxed);
+    let err = runtime_error(&mut kuhi, "+1 2");
+    assert!(matches!(err, RuntimeError::Cancelled));
+
+    cancel.store(false, Ordering::Relaxed);
+    kuhi.eval("+1 2").unwrap();
+}
@@ -0,0 +1,101 @@
+//! Reading source into tokens.
+
+use kuhi::{
+    err::SyntaxError,
+    parser::{parse, Loc, Token},
+    vm::Env,
+};
+
+fn start() -> Loc {
+    Loc {
+        start: 0,
+        end: 0,
+        line: 1,
+        column: 1,
+    }
+}
+
+/// What `src` leaves on top of the stack, as printed
+fn eval(src: &str) -> String {
+    let tokens = parse(src, &mut start()).unwrap();
+    let mut env = Env::new(tokens);
+    env.run().unwrap();
+
+    env.stack.last().unwrap().to_string()
+}
+
+#[test]
+fn strands_keep_every_element() {
+    assert_eq!(eval("1‿2"), "[1 2]");
+    assert_eq!(eval("1‿2‿3"), "[1 2 3]");
+    assert_eq!(eval("1‿2‿3‿4‿5"), "[1 2 3 4 5]");
+    assert_eq!(eval("+ 1‿2‿3 10‿20‿30"), "[11 22 33]");
+}
+
+#[test]
+fn line_comments_run_to_the_end_of_the_line() {
+    assert_eq!(eval("1 # 2"), "1");
+    assert_eq!(eval("1 #2 3"), "1");
+    assert_eq!(eval("# ⟨ ( [ are not opened\n1"), "1");
+    assert_eq!(eval("# 1\n+1 2"), "3");
+}
+
+#[test]
+fn block_comments_nest() {
+    assert_eq!(eval("#{ 2 }# 1"), "1");
+    assert_eq!(eval("2#{}#1"), "2");
+    assert_eq!(eval("#{ #{ 2 }# 3 }# 1"), "1");
+    assert_eq!(eval("#{ ( }# +1 #{ ⟨ }# 2"), "3");
+    assert_eq!(eval("#{\n2\n}# 1"), "1");
+    // Statements still split after wide chars in comments
+    assert_eq!(eval("#{ ⟨ π\n( }# +1\n#{ ⟩ }#\n2"), "3");
+}
+
+#[test]
+fn unterminated_block_comments_are_errors() {
+    for (src, column) in [("#{ 2", 1), ("1 #{ 2", 3), ("#{ #{ 2 }# 1", 1)] {
+        let errors = parse(src, &mut start()).unwrap_err();
+        assert_eq!(errors.len(), 1, "`{src}`");
+        assert!(matches!(errors[0].0, SyntaxError::UnterminatedComment));
+        assert_eq!(errors[0].1.column, column, "`{src}`");
+    }
+}
+
+#[test]
+fn comments_keep_locations_in_line() {
+    let locs = |src: &str| {
+        parse(src, &mut start())
+            .unwrap()
+            .into_iter()
+            .map(|(token, loc)| (token, loc.start, loc.line, loc.column))
+            .collect::<Vec<_>>()
+    };
+
+    // `α` takes two bytes but a single column
+    let tokens = locs("#{ α }# +");
+    assert!(matches!(tokens[..], [(Token::FunctionCall('+'), 9, 1, 9)]));
+    let tokens = locs("#{\n2\n}# +");
+    assert!(matches!(tokens[..], [(Token::FunctionCall('+'), 8, 3, 4)]));
+    let tokens = locs("# α\n+");
+    assert!(matches!(tokens[..], [(Token::FunctionCall('+'), 5, 2, 1)]));
+}
+
+#[test]
+fn definitions_cant_take_a_builtin_glyph() {
+    let tokens = parse("f ← (+1)\n⁻¹f ← (-1)", &mut start()).unwrap();
+    assert!(matches!(
+        tokens[..],
+        [
+            (Token::Definition('f', false, _), _),
+            (Token::Definition('f', true, _), _)
+        ]
+    ));
+
+    for src in ["ι ← (+1)", "⁻¹ι ← (-1)", "ⁿ ← (1)", "+ ← (1)", "f ← 1"] {
+        let errors = parse(src, &mut start()).unwrap_err();
+        assert!(
+            matches!(errors[..], [(SyntaxError::InvalidDefinition, _)]),
+            "`{src}`"
+        );
+    }
+}
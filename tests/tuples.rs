@@ -0,0 +1,97 @@
+//! Tuples, built with `⟨…⟩` and taken apart again.
+
+use kuhi::{
+    err::{ErrorKind, RuntimeError, SyntaxError},
+    Interpreter,
+};
+
+/// The whole stack `src` leaves, its top last, as printed
+fn eval(src: &str) -> Vec<String> {
+    let mut kuhi = Interpreter::new();
+    let stack = kuhi
+        .eval(src)
+        .unwrap_or_else(|errors| panic!("`{src}` failed: {errors:?}"));
+
+    stack.iter().map(|value| value.to_string()).collect()
+}
+
+fn runtime_error(src: &str) -> RuntimeError {
+    match Interpreter::new().eval(src).unwrap_err().remove(0).kind {
+        ErrorKind::Runtime(err) => err,
+        kind => panic!("expected a runtime error, got {kind:?}"),
+    }
+}
+
+#[test]
+fn tuples_hold_anything() {
+    assert_eq!(eval("⟨⟩"), ["⟨⟩"]);
+    assert_eq!(eval("⟨1 π 2‿3⟩"), ["⟨1 1π/1 [2 3]⟩"]);
+    assert_eq!(eval("⟨1 ⟨2 3⟩⟩"), ["⟨1 ⟨2 3⟩⟩"]);
+    // The body runs on a stack of its own
+    assert_eq!(eval("⟨+1 2 3⟩"), ["⟨3 3⟩"]);
+    assert_eq!(eval("5 ⟨1⟩"), ["⟨1⟩", "5"]);
+}
+
+#[test]
+fn unpacking_leaves_the_first_on_top() {
+    assert_eq!(eval("⊔⟨1 2 3⟩"), ["3", "2", "1"]);
+    assert_eq!(eval("⊔⟨⟩"), Vec::<String>::new());
+    assert_eq!(eval("+ ⊔⟨1 2⟩"), ["3"]);
+
+    assert!(matches!(
+        runtime_error("⊔3"),
+        RuntimeError::TypeMissmatch { .. }
+    ));
+}
+
+#[test]
+fn picking_counts_from_either_end() {
+    assert_eq!(eval("⊡0 ⟨1 π 2‿3⟩"), ["1"]);
+    assert_eq!(eval("⊡2 ⟨1 π 2‿3⟩"), ["[2 3]"]);
+    assert_eq!(eval("⊡⁻1 ⟨1 2⟩"), ["2"]);
+    assert_eq!(eval("⊡⁻2 ⟨1 2⟩"), ["1"]);
+
+    for (src, at) in [("⊡2 ⟨1 2⟩", 2), ("⊡⁻3 ⟨1 2⟩", -3)] {
+        match runtime_error(src) {
+            RuntimeError::IndexOutOfRange { index, len } => {
+                assert_eq!(index, at);
+                assert_eq!(len, 2);
+            }
+            err => panic!("expected an index out of range, got {err:?}"),
+        }
+    }
+
+    let err = Interpreter::new().eval("⊡0 ⟨⟩").unwrap_err().remove(0);
+    assert!(matches!(
+        err.kind,
+        ErrorKind::Runtime(RuntimeError::IndexOutOfRange { len: 0, .. })
+    ));
+    assert_eq!(err.note(), "the list is empty, so no index is valid");
+    let err = Interpreter::new().eval("⊡2 ⟨1 2⟩").unwrap_err().remove(0);
+    assert_eq!(
+        err.note(),
+        "valid indices go from 0 to 1, or from ⁻1 to ⁻2 counting from the end"
+    );
+}
+
+#[test]
+fn lists_of_tuples_hold_only_tuples() {
+    assert_eq!(eval("∾ ⟨1⟩ ⟨1 2⟩"), ["[⟨1⟩ ⟨1 2⟩]"]);
+    assert_eq!(eval("⊟2 ι5"), ["[⟨1 2⟩ ⟨3 4⟩ ⟨5⟩]"]);
+
+    for src in ["∾ 1 ⟨1 2⟩", "∾ 1‿2 ⟨1 2⟩", "∾ ⟨1 2⟩ ι2", "⋈ ι2 ⊟2 ι3"] {
+        assert!(
+            matches!(runtime_error(src), RuntimeError::ListTypeMissmatch { .. }),
+            "`{src}`"
+        );
+    }
+
+    // Strands only take numbers
+    for src in ["1‿⟨1 2⟩", "⟨1 2⟩‿1", "1‿2‿(+1)"] {
+        let err = Interpreter::new().eval(src).unwrap_err().remove(0);
+        assert!(
+            matches!(err.kind, ErrorKind::Syntax(SyntaxError::InvalidSymbol('‿'))),
+            "`{src}`"
+        );
+    }
+}
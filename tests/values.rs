@@ -0,0 +1,72 @@
+//! How values compare, which sorting and searching rely on.
+
+use std::cmp::Ordering;
+
+use kuhi::{array::Array, value::Value};
+use rug::{float::Special, Float, Integer, Rational};
+
+fn int(n: i32) -> Value {
+    Value::Integer(Integer::from(n))
+}
+
+fn float(x: f64) -> Value {
+    Value::Float(Float::with_val(53, x))
+}
+
+#[test]
+fn exact_and_inexact_numbers_tie() {
+    assert_eq!(int(2), float(2.0));
+    assert_eq!(Value::Rational(Rational::from((4, 2))), int(2));
+    assert_eq!(Value::Rational(Rational::from((1, 2))), float(0.5));
+    assert!(int(1) < float(1.5) && float(1.5) < int(2));
+
+    // Irrational values are never equal to rational ones
+    let pi = Value::Pi(Rational::from(1), 1);
+    assert!(Value::Rational(Rational::from((314, 100))) < pi);
+    assert!(pi < Value::Rational(Rational::from((315, 100))));
+    assert!(float(std::f64::consts::PI) < pi);
+}
+
+#[test]
+fn nan_and_undefined_go_last() {
+    let nan = Value::Float(Float::with_val(53, Special::Nan));
+    let infinity = Value::Infinity(1);
+
+    assert!(infinity < nan);
+    assert!(nan < Value::Undefined);
+    assert!(infinity < Value::Undefined);
+    assert!(Value::Infinity(-1) < int(-1000));
+
+    // Both are equal to themselves, so sorting them is still total
+    assert_eq!(nan.cmp(&nan), Ordering::Equal);
+    assert_eq!(Value::Undefined.cmp(&Value::Undefined), Ordering::Equal);
+
+    let mut values = vec![Value::Undefined, nan.clone(), int(3), infinity, int(-1)];
+    values.sort();
+    assert_eq!(
+        values,
+        [int(-1), int(3), Value::Infinity(1), nan, Value::Undefined]
+    );
+}
+
+#[test]
+fn kinds_go_in_a_fixed_order() {
+    let list = Value::List(vec![int(0)]);
+    let tuple = Value::Tuple(vec![int(0)]);
+    let string = Value::String("a".to_owned());
+
+    assert!(Value::Undefined < list);
+    assert!(list < tuple);
+    assert!(tuple < string);
+
+    // Element by element, a prefix going first
+    assert!(Value::List(vec![int(1), int(2)]) < Value::List(vec![int(1), int(3)]));
+    assert!(Value::List(vec![int(1)]) < Value::List(vec![int(1), int(0)]));
+    assert!(Value::List(vec![int(2)]) > Value::List(vec![int(1), int(9)]));
+    assert!(Value::Tuple(vec![]) < Value::Tuple(vec![int(0)]));
+
+    // Arrays as the nested lists they stand for
+    let array = Value::Array(Array::new(vec![2, 1], vec![int(1), int(2)]));
+    let rows = Value::List(vec![Value::List(vec![int(1)]), Value::List(vec![int(2)])]);
+    assert_eq!(array, rows);
+}
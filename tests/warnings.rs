@@ -0,0 +1,77 @@
+//! Warnings for numeric steps losing exactness or giving undefined values.
+
+use kuhi::{
+    err::{ErrorKind, Warning},
+    Interpreter,
+};
+
+fn warnings(kuhi: &mut Interpreter, src: &str) -> Vec<(Warning, String)> {
+    kuhi.eval(src).unwrap();
+    kuhi.warnings()
+        .into_iter()
+        .map(|diagnostic| match diagnostic.kind {
+            ErrorKind::Warning(warning) => (warning, kuhi.source()[diagnostic.span].to_owned()),
+            kind => panic!("expected a warning, got {kind:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn exact_values_becoming_floats_warn() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_warnings(true);
+
+    assert_eq!(
+        warnings(&mut kuhi, "+ π 1"),
+        [(Warning::Inexact, "+".to_owned())]
+    );
+    assert_eq!(
+        warnings(&mut kuhi, "◯ 1‿2"),
+        [(Warning::Inexact, "◯".to_owned())]
+    );
+
+    // Still exact, or already inexact
+    assert!(warnings(&mut kuhi, "× π 2").is_empty());
+    assert!(warnings(&mut kuhi, "+ 1 2").is_empty());
+    assert_eq!(warnings(&mut kuhi, "+ 1 ◯ 1").len(), 1);
+}
+
+#[test]
+fn undefined_values_warn() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_warnings(true);
+
+    assert_eq!(
+        warnings(&mut kuhi, "× ∞ 0"),
+        [(Warning::Undefined, "×".to_owned())]
+    );
+    assert_eq!(
+        warnings(&mut kuhi, "+ ∞ ⁻∞"),
+        [(Warning::Cancellation, "+".to_owned())]
+    );
+    assert_eq!(
+        warnings(&mut kuhi, "× ∞ ε"),
+        [(Warning::Cancellation, "×".to_owned())]
+    );
+
+    // Only where it first appears
+    assert_eq!(warnings(&mut kuhi, "+ 1 × ∞ 0").len(), 1);
+}
+
+#[test]
+fn steps_in_functions_warn_once() {
+    let mut kuhi = Interpreter::new();
+    kuhi.set_warnings(true);
+
+    let found = warnings(&mut kuhi, "f ← (+π)\n+ f 1 f 2");
+    assert_eq!(found, [(Warning::Inexact, "+".to_owned())]);
+    assert_eq!(kuhi.warnings().len(), 0);
+}
+
+#[test]
+fn warnings_are_off_by_default() {
+    let mut kuhi = Interpreter::new();
+
+    kuhi.eval("+ π 1").unwrap();
+    assert!(kuhi.warnings().is_empty());
+}